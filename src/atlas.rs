@@ -0,0 +1,581 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::{
+    Result, Unit, cgg::PartData, constants::MAX_CHANNEL_U8, debug::draw_rect_outline,
+    imageops::Rect,
+};
+
+/// Outline colour for regions referenced by more than one animation.
+pub const SHARED_REGION_COLOR: Rgba<u8> = Rgba([255, 255, 255, MAX_CHANNEL_U8]);
+/// Outline colour for regions only referenced by cgg frames that no animation uses.
+pub const UNANIMATED_REGION_COLOR: Rgba<u8> = Rgba([128, 128, 128, MAX_CHANNEL_U8]);
+/// Outline colour for opaque atlas regions no cgg part references.
+pub const UNUSED_REGION_COLOR: Rgba<u8> = Rgba([255, 0, 0, MAX_CHANNEL_U8]);
+
+/// Colours for regions used by a single animation, assigned in animation order.
+const ANIMATION_PALETTE: [Rgba<u8>; 8] = [
+    Rgba([0, 128, 255, MAX_CHANNEL_U8]),
+    Rgba([0, 255, 0, MAX_CHANNEL_U8]),
+    Rgba([255, 255, 0, MAX_CHANNEL_U8]),
+    Rgba([0, 255, 255, MAX_CHANNEL_U8]),
+    Rgba([255, 0, 255, MAX_CHANNEL_U8]),
+    Rgba([255, 128, 0, MAX_CHANNEL_U8]),
+    Rgba([128, 0, 255, MAX_CHANNEL_U8]),
+    Rgba([128, 255, 128, MAX_CHANNEL_U8]),
+];
+
+/// Frames used by one animation: its name and the cgg frame index of every cgs line.
+pub struct AnimationFrames {
+    pub name: String,
+    pub frame_indices: Vec<usize>,
+}
+
+/// A distinct atlas rectangle referenced by at least one cgg part.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtlasRegion {
+    pub rect: Rect,
+    /// Animations that draw this region, in animation order. Empty when only
+    /// unreferenced cgg frames use it.
+    pub animations: Vec<String>,
+}
+
+/// A cgg frame that no cgs file references.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreferencedFrame {
+    pub frame_idx: usize,
+    /// 1-based line of the frame in the cgg file.
+    pub line: usize,
+}
+
+/// Which parts of a unit's atlas are referenced by its cgg data and by each animation.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtlasReport {
+    pub unit_id: u32,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub animations: Vec<String>,
+    pub regions: Vec<AtlasRegion>,
+    /// Bounding boxes of connected opaque atlas areas that no referenced region covers.
+    pub unused_regions: Vec<Rect>,
+    pub unreferenced_frames: Vec<UnreferencedFrame>,
+}
+
+impl AtlasReport {
+    pub fn new(unit: &Unit, atlas: &DynamicImage, animations: &[AnimationFrames]) -> Self {
+        // Region rect -> indices of the animations that use it
+        let mut region_animations: HashMap<Rect, BTreeSet<usize>> = HashMap::new();
+        let mut regions_in_order: Vec<Rect> = Vec::new();
+        for part in unit.frames.iter().flatten() {
+            let rect = part_atlas_rect(part);
+            region_animations.entry(rect).or_insert_with(|| {
+                regions_in_order.push(rect);
+                BTreeSet::new()
+            });
+        }
+
+        let mut frame_used = vec![false; unit.frames.len()];
+        for (anim_idx, animation) in animations.iter().enumerate() {
+            for &frame_idx in &animation.frame_indices {
+                let Some(parts) = unit.frames.get(frame_idx) else {
+                    continue;
+                };
+                frame_used[frame_idx] = true;
+                for part in parts {
+                    if let Some(users) = region_animations.get_mut(&part_atlas_rect(part)) {
+                        users.insert(anim_idx);
+                    }
+                }
+            }
+        }
+
+        regions_in_order.sort_by_key(|rect| (rect.y, rect.x, rect.width, rect.height));
+        let regions: Vec<AtlasRegion> = regions_in_order
+            .into_iter()
+            .map(|rect| AtlasRegion {
+                rect,
+                animations: region_animations[&rect]
+                    .iter()
+                    .map(|&idx| animations[idx].name.clone())
+                    .collect(),
+            })
+            .collect();
+
+        let unreferenced_frames = frame_used
+            .iter()
+            .enumerate()
+            .filter(|(_, used)| !**used)
+            .map(|(frame_idx, _)| UnreferencedFrame {
+                frame_idx,
+                line: unit.frames[frame_idx]
+                    .first()
+                    .map_or(0, |part| part.line_index + 1),
+            })
+            .collect();
+
+        let referenced: Vec<Rect> = regions.iter().map(|region| region.rect).collect();
+        let (atlas_width, atlas_height) = atlas.dimensions();
+
+        Self {
+            unit_id: unit.id,
+            atlas_width,
+            atlas_height,
+            animations: animations.iter().map(|a| a.name.clone()).collect(),
+            regions,
+            unused_regions: find_unused_regions(&atlas.to_rgba8(), &referenced),
+            unreferenced_frames,
+        }
+    }
+
+    /// Outline colour of a referenced region on the atlas map.
+    pub fn region_color(&self, region: &AtlasRegion) -> Rgba<u8> {
+        match region.animations.as_slice() {
+            [] => UNANIMATED_REGION_COLOR,
+            [name] => {
+                let idx = self.animations.iter().position(|a| a == name).unwrap_or(0);
+                animation_color(idx)
+            }
+            _ => SHARED_REGION_COLOR,
+        }
+    }
+}
+
+/// The rectangle of the source atlas a part is cut from.
+pub fn part_atlas_rect(part: &PartData) -> Rect {
+    Rect {
+        x: part.atlas_x as i32,
+        y: part.atlas_y as i32,
+        width: part.atlas_width,
+        height: part.atlas_height,
+    }
+}
+
+pub fn animation_color(idx: usize) -> Rgba<u8> {
+    ANIMATION_PALETTE[idx % ANIMATION_PALETTE.len()]
+}
+
+/// Renders the atlas at half brightness with every unused opaque region outlined in red
+/// and every referenced region outlined in its animation colour on top.
+pub fn render_map(atlas: &DynamicImage, report: &AtlasReport) -> RgbaImage {
+    let mut map = atlas.to_rgba8();
+    for pixel in map.pixels_mut() {
+        let Rgba([r, g, b, a]) = *pixel;
+        *pixel = Rgba([r / 2, g / 2, b / 2, a]);
+    }
+
+    for rect in &report.unused_regions {
+        draw_rect_outline(
+            &mut map,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            UNUSED_REGION_COLOR,
+        );
+    }
+    for region in &report.regions {
+        let rect = region.rect;
+        let color = report.region_color(region);
+        draw_rect_outline(&mut map, rect.x, rect.y, rect.width, rect.height, color);
+    }
+
+    map
+}
+
+/// A cgg part that draws an atlas slice.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartUse {
+    pub frame_idx: usize,
+    /// Index of the part within its cgg line.
+    pub part_index: usize,
+    /// 1-based line of the frame in the cgg file.
+    pub line: usize,
+}
+
+/// A distinct atlas rectangle cut out as its own image.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtlasSlice {
+    pub file: String,
+    pub rect: Rect,
+    pub uses: Vec<PartUse>,
+}
+
+/// Index written next to the slice images.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SliceIndex {
+    pub unit_id: u32,
+    pub slices: Vec<AtlasSlice>,
+}
+
+impl SliceIndex {
+    /// Collects every distinct atlas rectangle referenced by the unit's cgg parts, ordered
+    /// top to bottom, left to right, with every part that uses it. Rectangles extending past
+    /// the `atlas_size` atlas are clamped to it and empty ones are left out, with a warning
+    /// for each.
+    pub fn new(unit: &Unit, atlas_size: (u32, u32)) -> Self {
+        let (atlas_width, atlas_height) = atlas_size;
+        let mut slices: HashMap<Rect, AtlasSlice> = HashMap::new();
+        let mut warned = HashSet::new();
+        for (frame_idx, parts) in unit.frames.iter().enumerate() {
+            for part in parts {
+                let part_rect = part_atlas_rect(part);
+                let rect = clamp_to_atlas(part_rect, atlas_size);
+                let Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } = part_rect;
+                let location =
+                    || format!("part {} on cgg line {}", part.index, part.line_index + 1);
+                if width == 0 || height == 0 {
+                    if warned.insert(part_rect) {
+                        log::warn!("Skipping the empty atlas rectangle of {}", location());
+                    }
+                    continue;
+                }
+                if rect != part_rect && warned.insert(part_rect) {
+                    log::warn!(
+                        "Atlas rectangle {x},{y} {width}x{height} of {} extends past the \
+                         {atlas_width}x{atlas_height} atlas",
+                        location()
+                    );
+                }
+                if rect.width == 0 || rect.height == 0 {
+                    continue;
+                }
+
+                slices
+                    .entry(rect)
+                    .or_insert_with(|| AtlasSlice {
+                        file: slice_file_name(rect),
+                        rect,
+                        uses: Vec::new(),
+                    })
+                    .uses
+                    .push(PartUse {
+                        frame_idx,
+                        part_index: part.index,
+                        line: part.line_index + 1,
+                    });
+            }
+        }
+
+        let mut slices: Vec<AtlasSlice> = slices.into_values().collect();
+        slices.sort_by_key(|slice| {
+            (
+                slice.rect.y,
+                slice.rect.x,
+                slice.rect.width,
+                slice.rect.height,
+            )
+        });
+        for slice in &mut slices {
+            slice
+                .uses
+                .sort_by_key(|part| (part.frame_idx, part.part_index));
+        }
+
+        Self {
+            unit_id: unit.id,
+            slices,
+        }
+    }
+
+    /// Writes every slice as a PNG cut straight from `atlas`, without the part's flips,
+    /// rotation, blending or opacity, plus `index.json` into `dir`.
+    pub fn save(&self, atlas: &DynamicImage, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for slice in &self.slices {
+            let Rect {
+                x,
+                y,
+                width,
+                height,
+            } = slice.rect;
+            atlas
+                .crop_imm(x as u32, y as u32, width, height)
+                .save(dir.join(&slice.file))?;
+        }
+        std::fs::write(dir.join("index.json"), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The part of `rect` inside an atlas of `(width, height)`; empty when it lies outside.
+fn clamp_to_atlas(rect: Rect, (width, height): (u32, u32)) -> Rect {
+    let x = (rect.x.max(0) as u32).min(width);
+    let y = (rect.y.max(0) as u32).min(height);
+    Rect {
+        x: x as i32,
+        y: y as i32,
+        width: rect.width.min(width - x),
+        height: rect.height.min(height - y),
+    }
+}
+
+/// `{x}_{y}_{width}x{height}.png`, named after the slice's atlas rectangle.
+pub fn slice_file_name(rect: Rect) -> String {
+    format!("{}_{}_{}x{}.png", rect.x, rect.y, rect.width, rect.height)
+}
+
+/// Returns the bounding boxes of 8-connected groups of non-transparent pixels that lie
+/// outside every rect in `referenced`, ordered top to bottom, left to right.
+fn find_unused_regions(atlas: &RgbaImage, referenced: &[Rect]) -> Vec<Rect> {
+    let (width, height) = atlas.dimensions();
+    let idx = |x: u32, y: u32| (y * width + x) as usize;
+
+    // Pixels still to visit: opaque and not covered by a referenced rect
+    let mut pending: Vec<bool> = atlas.pixels().map(|p| p.0[3] > 0).collect();
+    for rect in referenced {
+        let x0 = rect.x.clamp(0, width as i32) as u32;
+        let y0 = rect.y.clamp(0, height as i32) as u32;
+        let x1 = (rect.x + rect.width as i32).clamp(0, width as i32) as u32;
+        let y1 = (rect.y + rect.height as i32).clamp(0, height as i32) as u32;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                pending[idx(x, y)] = false;
+            }
+        }
+    }
+
+    let mut unused = Vec::new();
+    let mut stack = Vec::new();
+    for (y, x) in (0..height).flat_map(|y| (0..width).map(move |x| (y, x))) {
+        if !pending[idx(x, y)] {
+            continue;
+        }
+
+        pending[idx(x, y)] = false;
+        stack.push((x, y));
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (x, y, x, y);
+
+        while let Some((px, py)) = stack.pop() {
+            min_x = min_x.min(px);
+            min_y = min_y.min(py);
+            max_x = max_x.max(px);
+            max_y = max_y.max(py);
+
+            for ny in py.saturating_sub(1)..=(py + 1).min(height - 1) {
+                for nx in px.saturating_sub(1)..=(px + 1).min(width - 1) {
+                    if pending[idx(nx, ny)] {
+                        pending[idx(nx, ny)] = false;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+
+        unused.push(Rect {
+            x: min_x as i32,
+            y: min_y as i32,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        });
+    }
+
+    unused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn part(atlas_x: u32, atlas_y: u32, line_index: usize) -> PartData {
+        PartData {
+            atlas_x,
+            atlas_y,
+            atlas_width: 2,
+            atlas_height: 2,
+            line_index,
+            ..Default::default()
+        }
+    }
+
+    fn opaque_atlas() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            10,
+            4,
+            Rgba([200, 200, 200, MAX_CHANNEL_U8]),
+        ))
+    }
+
+    #[test]
+    fn test_atlas_report() {
+        let unit = Unit {
+            id: 1,
+            frames: vec![
+                vec![part(0, 0, 0)],
+                vec![part(0, 0, 1), part(2, 0, 1)],
+                vec![part(4, 0, 2)],
+            ],
+            ..Default::default()
+        };
+        let animations = vec![
+            AnimationFrames {
+                name: "atk".to_string(),
+                frame_indices: vec![0, 0],
+            },
+            AnimationFrames {
+                name: "idle".to_string(),
+                frame_indices: vec![1],
+            },
+        ];
+
+        let report = AtlasReport::new(&unit, &opaque_atlas(), &animations);
+
+        assert_eq!(report.regions.len(), 3);
+        assert_eq!(report.regions[0].animations, vec!["atk", "idle"]);
+        assert_eq!(report.regions[1].animations, vec!["idle"]);
+        assert!(report.regions[2].animations.is_empty());
+        assert_eq!(report.region_color(&report.regions[0]), SHARED_REGION_COLOR);
+        assert_eq!(report.region_color(&report.regions[1]), animation_color(1));
+        assert_eq!(
+            report.region_color(&report.regions[2]),
+            UNANIMATED_REGION_COLOR
+        );
+
+        assert_eq!(report.unreferenced_frames.len(), 1);
+        assert_eq!(report.unreferenced_frames[0].frame_idx, 2);
+        assert_eq!(report.unreferenced_frames[0].line, 3);
+
+        // Everything right of the parts and below them forms one connected area
+        assert_eq!(report.unused_regions.len(), 1);
+        assert_eq!(
+            report.unused_regions[0],
+            Rect {
+                x: 0,
+                y: 0,
+                width: 10,
+                height: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_unused_regions_separates_components() {
+        let mut atlas = RgbaImage::new(8, 3);
+        for (x, y) in [(0, 0), (1, 1), (5, 0), (6, 0), (7, 2)] {
+            atlas.put_pixel(x, y, Rgba([0, 0, 0, MAX_CHANNEL_U8]));
+        }
+        let referenced = [Rect {
+            x: 7,
+            y: 2,
+            width: 1,
+            height: 1,
+        }];
+
+        let unused = find_unused_regions(&atlas, &referenced);
+
+        assert_eq!(
+            unused,
+            vec![
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: 2,
+                    height: 2,
+                },
+                Rect {
+                    x: 5,
+                    y: 0,
+                    width: 2,
+                    height: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_map_outlines_regions() {
+        let unit = Unit {
+            id: 1,
+            frames: vec![vec![part(0, 0, 0)]],
+            ..Default::default()
+        };
+        let animations = vec![AnimationFrames {
+            name: "atk".to_string(),
+            frame_indices: vec![0],
+        }];
+        let atlas = opaque_atlas();
+        let report = AtlasReport::new(&unit, &atlas, &animations);
+
+        let map = render_map(&atlas, &report);
+
+        assert_eq!(*map.get_pixel(0, 0), animation_color(0));
+        assert_eq!(*map.get_pixel(9, 3), UNUSED_REGION_COLOR);
+        assert_eq!(*map.get_pixel(5, 2), Rgba([100, 100, 100, MAX_CHANNEL_U8]));
+    }
+
+    #[test]
+    fn test_slice_index_dedupes_rects() {
+        let unit = Unit {
+            id: 1,
+            frames: vec![
+                vec![part(4, 0, 0), part(0, 0, 0)],
+                vec![PartData {
+                    index: 1,
+                    ..part(0, 0, 2)
+                }],
+            ],
+            ..Default::default()
+        };
+
+        let index = SliceIndex::new(&unit, (10, 4));
+
+        assert_eq!(index.slices.len(), 2);
+        assert_eq!(index.slices[0].file, "0_0_2x2.png");
+        assert_eq!(index.slices[0].uses.len(), 2);
+        assert_eq!(index.slices[0].uses[1].frame_idx, 1);
+        assert_eq!(index.slices[0].uses[1].part_index, 1);
+        assert_eq!(index.slices[0].uses[1].line, 3);
+        assert_eq!(index.slices[1].file, "4_0_2x2.png");
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("slices");
+        index.save(&opaque_atlas(), &dir).unwrap();
+
+        let slice = image::open(dir.join("4_0_2x2.png")).unwrap();
+        assert_eq!(slice.dimensions(), (2, 2));
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("index.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["unitId"], 1);
+        assert_eq!(json["slices"][0]["uses"][0]["partIndex"], 0);
+    }
+
+    #[test]
+    fn test_slice_index_clamps_rects() {
+        let unit = Unit {
+            id: 1,
+            frames: vec![vec![
+                part(9, 0, 0),
+                part(20, 0, 0),
+                PartData {
+                    atlas_width: 0,
+                    ..part(0, 0, 0)
+                },
+            ]],
+            ..Default::default()
+        };
+
+        // Only the part of the rect inside the 10x4 atlas is kept and named
+        let index = SliceIndex::new(&unit, (10, 4));
+        assert_eq!(index.slices.len(), 1);
+        assert_eq!(index.slices[0].file, "9_0_1x2.png");
+
+        let temp_dir = TempDir::new().unwrap();
+        index.save(&opaque_atlas(), temp_dir.path()).unwrap();
+        let slice = image::open(temp_dir.path().join("9_0_1x2.png")).unwrap();
+        assert_eq!(slice.dimensions(), (1, 2));
+    }
+}
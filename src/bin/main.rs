@@ -1,15 +1,30 @@
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, Parser, Subcommand, parser::ValueSource};
 use ffbetool::{
-    self, FfbeError,
-    cgg::{self},
-    cgs::{self, process_frames},
-    character_db,
-    constants::{FRAME_PADDING, REMOTE_DATA_FILE},
-    discovery, metadata, validation,
+    self, FfbeError, atlas, cgs, character_db,
+    config::{self, Config},
+    constants::REMOTE_DATA_FILE,
+    discovery,
+    imageops::Scaler,
+    info::UnitInfo,
+    layout::{Background, CellAlign, LayoutOptions, Padding},
+    lint,
+    manifest::Manifest,
+    output::{
+        DEFAULT_TEMPLATE, OutputKind, OutputPaths, OutputTemplate, OverwritePolicy, WriteOutcome,
+    },
+    progress::{self, Progress, ProgressHook, ProgressTracker},
+    render::{self, PlannedOutput, RenderContext, RenderOptions, report_write},
+    report::{ErrorReport, OutputReport, Reporter, RunReport},
+    validation, watch,
 };
-use image::imageops;
-use std::io::BufRead;
+use indicatif::{HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub enum UnitIdentifier {
@@ -17,6 +32,15 @@ pub enum UnitIdentifier {
     Name(String),
 }
 
+impl std::fmt::Display for UnitIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnitIdentifier::Id(id) => write!(f, "{id}"),
+            UnitIdentifier::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
 impl FromStr for UnitIdentifier {
     type Err = std::convert::Infallible;
 
@@ -29,39 +53,202 @@ impl FromStr for UnitIdentifier {
     }
 }
 
-#[derive(Parser, Clone)]
+#[derive(Parser)]
 #[command(name = "ffbetool")]
 #[command(about = "Tool to assemble Final Fantasy Brave Exvius sprite sheets")]
+#[command(
+    after_help = "Running `ffbetool <UID> [OPTIONS]` without a subcommand is the same as `ffbetool render`."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Only print errors
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        conflicts_with = "verbose",
+        overrides_with = "no_quiet"
+    )]
+    quiet: bool,
+
+    /// Turns off `quiet` set in `ffbetool.toml`; never read, see `NegatedFlags`
+    #[arg(
+        long = "no-quiet",
+        global = true,
+        overrides_with = "quiet",
+        hide = true
+    )]
+    #[allow(dead_code)]
+    no_quiet: bool,
+
+    /// Print more details; repeat for even more (-vv)
+    #[arg(short = 'v', long = "verbose", global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Assemble spritesheets and animations for a unit (the default)
+    Render(Box<Args>),
+    /// Summarise a unit's cgg data, atlas and animations
+    Info(InfoArgs),
+    /// List the animations found for a unit
+    List(UnitArgs),
+    /// Search the character database by name
+    Search(SearchArgs),
+    /// Check a unit's atlas, cgg and cgs files for problems
+    Lint(UnitArgs),
+    /// Manage the character database
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// Save a map of the atlas regions each animation uses and report unused regions and
+    /// unreferenced cgg frames
+    AtlasMap(AtlasArgs),
+    /// Cut every distinct atlas rectangle used by the cgg data into its own PNG with a JSON
+    /// index of the parts using it
+    Slice(AtlasArgs),
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Download the latest character database
+    Update {
+        /// Where to save the database
+        #[arg(short = 'o', long = "output", default_value = CHARACTER_DB_FILE)]
+        output: String,
+    },
+}
+
+#[derive(clap::Args, Clone)]
+struct UnitArgs {
+    /// The unit id or character name
+    uid: UnitIdentifier,
+
+    /// The source input directory
+    #[arg(short = 'i', long = "input", default_value = ".")]
+    input_dir: String,
+}
+
+#[derive(clap::Args, Clone)]
+struct AtlasArgs {
+    #[command(flatten)]
+    unit: UnitArgs,
+
+    /// The output directory
+    #[arg(short = 'o', long = "output", default_value = ".")]
+    output_dir: String,
+
+    /// Save json file
+    #[arg(short = 'j', long = "json")]
+    save_json: bool,
+
+    /// Output path template relative to the output directory, see `render --help`; {anim}
+    /// is `atlas-map` or `slices`
+    #[arg(long = "filename", default_value = DEFAULT_TEMPLATE)]
+    filename: OutputTemplate,
+
+    /// What to do with existing output files: always, never, if-newer or error
+    #[arg(long = "overwrite", default_value_t = OverwritePolicy::Always)]
+    overwrite: OverwritePolicy,
+
+    /// List the files that would be written without writing them
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(clap::Args, Clone)]
+struct InfoArgs {
+    #[command(flatten)]
+    unit: UnitArgs,
+
+    /// Print the report as JSON
+    #[arg(short = 'j', long = "json")]
+    json: bool,
+}
+
+#[derive(clap::Args, Clone)]
+struct SearchArgs {
+    /// Full or partial character name, case-insensitive
+    query: String,
+}
+
+#[derive(Parser, Clone)]
 struct Args {
-    /// The unit id
+    /// The unit id or character name
     uid: UnitIdentifier,
 
-    /// The animation name (if not specified, all animations will be processed)
-    #[arg(short = 'a', long = "anim")]
-    anim: Option<String>,
+    /// Animation names or glob patterns such as `limit_*`; repeatable or comma separated
+    /// (if not specified, all animations will be processed)
+    #[arg(short = 'a', long = "anim", value_delimiter = ',')]
+    anim: Vec<String>,
+
+    /// Skip animations matching these names or glob patterns; repeatable or comma separated
+    #[arg(long = "exclude", value_delimiter = ',')]
+    exclude: Vec<String>,
 
     /// The number of columns
     #[arg(short = 'c', long = "columns", default_value = "0")]
     columns: usize,
 
     /// Include empty frames
-    #[arg(short = 'e', long = "empty")]
+    #[arg(short = 'e', long = "empty", overrides_with = "no_empty")]
     include_empty: bool,
 
-    /// Verbose logs
-    #[arg(short = 'v', long = "verbose")]
-    verbose: bool,
+    /// Collapse consecutive identical frames into one in animated outputs
+    #[arg(long = "collapse", overrides_with = "no_collapse")]
+    collapse: bool,
+
+    /// Crop every selected animation of the unit to the same bounds so they share an origin point
+    #[arg(long = "shared-bounds", overrides_with = "no_shared_bounds")]
+    shared_bounds: bool,
+
+    /// Also export every frame as a folder of per-part PNG layers
+    #[arg(long = "layers", overrides_with = "no_layers")]
+    save_layers: bool,
+
+    /// Draw part boxes, origin cross and frame bounds on every output for debugging
+    #[arg(long = "debug-overlay", overrides_with = "no_debug_overlay")]
+    debug_overlay: bool,
+
+    /// Write a JSON report of every animation's status, outputs and timings to this file
+    #[arg(long = "report")]
+    report: Option<String>,
+
+    /// Stream one JSON line per finished animation to this file, or to stdout with `-`
+    #[arg(long = "report-ndjson")]
+    report_ndjson: Option<String>,
+
+    /// Re-render animations even when their inputs and options are unchanged
+    #[arg(long = "force", overrides_with = "no_force")]
+    force: bool,
+
+    /// List the files that would be written, with their sizes and formats, without writing
+    #[arg(long = "dry-run", overrides_with = "no_dry_run")]
+    dry_run: bool,
+
+    /// Keep running and re-render whenever the unit's cgg, cgs or atlas files change
+    #[arg(long = "watch", overrides_with = "no_watch")]
+    watch: bool,
+
+    /// Render up to this many animations at once, each keeping its frames in memory, on as
+    /// many threads (0 uses one per CPU core)
+    #[arg(long = "jobs", default_value = "0")]
+    jobs: usize,
 
     /// Save json file
-    #[arg(short = 'j', long = "json")]
+    #[arg(short = 'j', long = "json", overrides_with = "no_json")]
     save_json: bool,
 
     /// Save animated gif
-    #[arg(long = "gif")]
+    #[arg(long = "gif", overrides_with = "no_gif")]
     save_gif: bool,
 
     /// Save animated png (APNG)
-    #[arg(long = "apng")]
+    #[arg(long = "apng", overrides_with = "no_apng")]
     save_apng: bool,
 
     /// The source input directory
@@ -71,123 +258,580 @@ struct Args {
     /// The output directory
     #[arg(short = 'o', long = "output", default_value = ".")]
     output_dir: String,
+
+    /// Output path template relative to the output directory, e.g.
+    /// `{name}/{anim}/{uid}_{anim}.{ext}`. Placeholders: {uid}, {name}, {rarity}, {anim},
+    /// {format} (sheet, json, apng, gif or layers), {ext} and {suffix} (-anim or -layers)
+    #[arg(long = "filename", default_value = DEFAULT_TEMPLATE)]
+    filename: OutputTemplate,
+
+    /// What to do with existing output files: always, never (keep them), if-newer (replace
+    /// them when an input was modified since) or error
+    #[arg(long = "overwrite", default_value_t = OverwritePolicy::Always)]
+    overwrite: OverwritePolicy,
+
+    /// Upscale factor applied to every output, up to 16
+    #[arg(long = "scale", default_value = "1")]
+    scale: u32,
+
+    /// Upscaling algorithm: nearest, or scale2x (pixel-art smoothing, factors 2-4)
+    #[arg(long = "scaler", default_value_t = Scaler::Nearest)]
+    scaler: Scaler,
+
+    /// Apply a named profile from ffbetool.toml
+    #[arg(long = "profile")]
+    profile: Option<String>,
+
+    /// Read defaults from this file instead of the user and project ffbetool.toml
+    #[arg(long = "config")]
+    config: Option<String>,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+
+    #[command(flatten)]
+    negated_flags: NegatedFlags,
 }
 
-#[derive(Clone, Copy)]
-enum AnimFileType {
-    Gif,
-    Apng,
-    None,
+/// `--no-<flag>` forms that turn off a flag set in `ffbetool.toml`. They are never read:
+/// each overrides the flag it negates and the other way round, so the last one given wins.
+#[derive(clap::Args, Clone)]
+#[allow(dead_code)]
+struct NegatedFlags {
+    #[arg(long = "no-empty", overrides_with = "include_empty", hide = true)]
+    no_empty: bool,
+    #[arg(long = "no-collapse", overrides_with = "collapse", hide = true)]
+    no_collapse: bool,
+    #[arg(
+        long = "no-shared-bounds",
+        overrides_with = "shared_bounds",
+        hide = true
+    )]
+    no_shared_bounds: bool,
+    #[arg(long = "no-layers", overrides_with = "save_layers", hide = true)]
+    no_layers: bool,
+    #[arg(
+        long = "no-debug-overlay",
+        overrides_with = "debug_overlay",
+        hide = true
+    )]
+    no_debug_overlay: bool,
+    #[arg(long = "no-force", overrides_with = "force", hide = true)]
+    no_force: bool,
+    #[arg(long = "no-dry-run", overrides_with = "dry_run", hide = true)]
+    no_dry_run: bool,
+    #[arg(long = "no-watch", overrides_with = "watch", hide = true)]
+    no_watch: bool,
+    #[arg(long = "no-json", overrides_with = "save_json", hide = true)]
+    no_json: bool,
+    #[arg(long = "no-gif", overrides_with = "save_gif", hide = true)]
+    no_gif: bool,
+    #[arg(long = "no-apng", overrides_with = "save_apng", hide = true)]
+    no_apng: bool,
 }
 
-impl From<&str> for AnimFileType {
-    fn from(value: &str) -> Self {
-        match value {
-            "apng" => AnimFileType::Apng,
-            "gif" => AnimFileType::Gif,
-            _ => AnimFileType::None,
+#[derive(clap::Args, Clone)]
+struct LayoutArgs {
+    /// Frame padding in pixels: ALL, VERTICAL,HORIZONTAL or TOP,RIGHT,BOTTOM,LEFT
+    #[arg(long = "padding", default_value_t = Padding::default())]
+    padding: Padding,
+
+    /// Round frame sizes up to a multiple of N pixels, or to a power of two with `pow2`
+    #[arg(long = "cell-align", default_value_t = CellAlign::None)]
+    cell_align: CellAlign,
+
+    /// Spacing in pixels between spritesheet cells
+    #[arg(long = "spacing", default_value = "0")]
+    spacing: u32,
+
+    /// Background colour: transparent, #RRGGBB, #RRGGBBAA or checkerboard[:SIZE]
+    #[arg(long = "background", default_value_t = Background::Transparent)]
+    background: Background,
+}
+
+impl From<&Args> for RenderOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            input_dir: args.input_dir.clone(),
+            columns: args.columns,
+            include_empty: args.include_empty,
+            collapse: args.collapse,
+            shared_bounds: args.shared_bounds,
+            save_layers: args.save_layers,
+            debug_overlay: args.debug_overlay,
+            save_json: args.save_json,
+            save_gif: args.save_gif,
+            save_apng: args.save_apng,
+            scale: args.scale,
+            scaler: args.scaler,
+            layout: LayoutOptions::from(&args.layout),
+            force: args.force,
+            dry_run: args.dry_run,
+            jobs: args.jobs,
         }
     }
 }
 
-fn main() -> ffbetool::Result<()> {
-    let args = Args::parse();
-    let char_db = match character_db::Db::from_file("character_data.json") {
-        Ok(db) => db,
-        Err(_) => {
-            let body = ureq::get(REMOTE_DATA_FILE).call()?.into_string()?;
-            serde_json::from_str(&body)?
+impl From<&LayoutArgs> for LayoutOptions {
+    fn from(args: &LayoutArgs) -> Self {
+        Self {
+            padding: args.padding,
+            cell_align: args.cell_align,
+            spacing: args.spacing,
+            background: args.background,
         }
-    };
+    }
+}
+
+const CHARACTER_DB_FILE: &str = "character_data.json";
+
+/// `{anim}` of the outputs of `atlas-map` and `slice`, which belong to the whole unit.
+const ATLAS_MAP_NAME: &str = "atlas-map";
+const SLICES_NAME: &str = "slices";
 
-    let uid: u32 = match &args.uid {
-        UnitIdentifier::Id(id) => *id,
-        UnitIdentifier::Name(name) => match char_db.find_by_name(name) {
-            character_db::LookupResult::Found(id) => id,
-            character_db::LookupResult::NotFound => {
-                return Err(FfbeError::CharacterNotFound(name.to_owned()));
+/// How often `--watch` polls the input files.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            print_diagnostic(&err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> ffbetool::Result<()> {
+    let args = with_default_subcommand(std::env::args_os().collect());
+    let cli = Cli::parse_from(with_config_defaults(args)?);
+    init_logger(log_level(cli.quiet, cli.verbose));
+
+    match cli.command {
+        Command::Render(args) if args.watch => watch(&args),
+        Command::Render(args) => render(&args),
+        Command::Info(args) => info(&args),
+        Command::List(args) => list(&args),
+        Command::Search(args) => search(&args),
+        Command::Lint(args) => lint(&args),
+        Command::Db { command } => match command {
+            DbCommand::Update { output } => update_db(&output),
+        },
+        Command::AtlasMap(args) => save_atlas_map(&args),
+        Command::Slice(args) => save_atlas_slices(&args),
+    }
+}
+
+/// Prints the error followed by each of its causes on its own line:
+///
+/// ```text
+/// error: Failed to render animation atk of unit 123
+///   caused by: Failed to read input/unit_atk_cgs_123.csv
+///   caused by: Invalid delay value at line 3: 'x'
+/// ```
+fn print_diagnostic(err: &FfbeError) {
+    eprintln!("error: {err}");
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        eprintln!("  caused by: {cause}");
+        source = cause.source();
+    }
+}
+
+/// Terminal progress bars of the running render, drawn on stderr.
+static PROGRESS_BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Writes log records to stderr: errors and warnings prefixed with their level, info as
+/// plain lines and debug and trace with their target.
+struct CliLogger;
+
+impl log::Log for CliLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Hide the progress bars while printing so the line does not end up inside them
+        PROGRESS_BARS.suspend(|| match record.level() {
+            log::Level::Error => eprintln!("error: {}", record.args()),
+            log::Level::Warn => eprintln!("warning: {}", record.args()),
+            log::Level::Info => eprintln!("{}", record.args()),
+            log::Level::Debug | log::Level::Trace => {
+                eprintln!("[{}] {}", record.target(), record.args())
             }
-            character_db::LookupResult::Multiple(similar_matches) => {
-                println!("Did you mean one of the following? Try again with the associated uid.");
-                let message = similar_matches
-                    .iter()
-                    .map(|(uid, char_info)| format!("{uid} -> {}", char_info.name))
-                    .collect::<Vec<String>>()
-                    .join("\n\t");
-                println!("\n\t{message}\n\n");
-                return Err(FfbeError::CharacterNotFound(name.to_owned()));
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// `-q` shows errors only, the default adds warnings and progress, `-v` adds debug details
+/// and `-vv` everything.
+fn log_level(quiet: bool, verbose: u8) -> log::LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    }
+}
+
+fn init_logger(level: log::LevelFilter) {
+    static LOGGER: CliLogger = CliLogger;
+    // Only fails if a logger was already set, which is then kept
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Returns the position of the first argument after the global flags (`-q`, `-v`, `-vv`,
+/// ...) that may come before the subcommand. `--config` is a `render` option, so it
+/// starts the implied `render` arguments like any other option.
+fn subcommand_index(args: &[OsString]) -> usize {
+    let mut index = 1;
+    while let Some(arg) = args.get(index).and_then(|arg| arg.to_str()) {
+        index += match arg {
+            "--quiet" | "--verbose" => 1,
+            _ if arg.len() > 1
+                && arg.starts_with('-')
+                && arg[1..].chars().all(|c| matches!(c, 'q' | 'v')) =>
+            {
+                1
             }
-        },
+            _ => break,
+        };
+    }
+    index
+}
+
+/// Inserts `render` after the program name unless the first argument after the leading
+/// global flags already names a subcommand or asks for help, so `ffbetool <uid> ...` keeps
+/// working. The global flags are accepted after `render` too.
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let Some(first) = args
+        .get(subcommand_index(&args))
+        .and_then(|arg| arg.to_str())
+    else {
+        return args;
     };
 
-    validation::validate_input_args(uid, &args.input_dir, args.anim.as_deref())?;
-    validation::validate_output_dir(&args.output_dir)?;
+    let is_subcommand = first == "help"
+        || Cli::command()
+            .get_subcommands()
+            .any(|command| command.get_name() == first);
+    if !is_subcommand && !matches!(first, "-h" | "--help") {
+        args.insert(1, OsString::from("render"));
+    }
 
-    let anim_file_type = determine_animation_file_type(&args);
+    args
+}
 
-    // Load and process frame data
-    let frames = load_cgg_frames(uid, &args.input_dir)?;
-    let mut unit = create_unit(uid, frames);
-    let src_img = ffbetool::imageops::load_source_image(uid, &args.input_dir)?;
+/// Adds the options configured in `ffbetool.toml` to a `render` invocation for every
+/// option not given on the command line.
+fn with_config_defaults(args: Vec<OsString>) -> ffbetool::Result<Vec<OsString>> {
+    let matches = Cli::command().get_matches_from(&args);
+    let Some(("render", render_matches)) = matches.subcommand() else {
+        return Ok(args);
+    };
+
+    let explicit = render_matches.get_one::<String>("config");
+    let config = Config::load(explicit.map(std::path::Path::new))?;
+    apply_config(args, render_matches, &config)
+}
 
-    // Process animations based on whether a specific animation was requested
-    match args.anim.as_deref() {
-        Some(anim_name) => {
-            process_single_animation(&args, uid, &mut unit, &src_img, anim_name, anim_file_type)?;
+/// Inserts the resolved config options right after the `render` subcommand, where the
+/// command line arguments that follow can still be parsed as before.
+fn apply_config(
+    mut args: Vec<OsString>,
+    render_matches: &ArgMatches,
+    config: &Config,
+) -> ffbetool::Result<Vec<OsString>> {
+    let unit = render_matches
+        .get_raw("uid")
+        .and_then(|mut values| values.next())
+        .map(|uid| uid.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let profile = render_matches.get_one::<String>("profile");
+    let options = config.resolve(profile.map(String::as_str), &unit)?;
+
+    let cli_command = Cli::command();
+    let render_command = cli_command
+        .find_subcommand("render")
+        .expect("render subcommand is defined");
+    let global_args: Vec<_> = cli_command
+        .get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .collect();
+    let mut config_args = Vec::new();
+    for (key, value) in &options {
+        let arg = render_command
+            .get_arguments()
+            .chain(global_args.iter().copied())
+            .find(|arg| arg.get_long() == Some(key.as_str()))
+            .filter(|_| !matches!(key.as_str(), "profile" | "config" | "help"))
+            .ok_or_else(|| {
+                FfbeError::InvalidInput(format!(
+                    "Unknown option '{key}' in {}",
+                    config::CONFIG_FILE
+                ))
+            })?;
+
+        // A flag also counts as given when its `--no-` form is
+        let negation = format!("no-{key}");
+        let given = render_command
+            .get_arguments()
+            .chain(global_args.iter().copied())
+            .filter(|candidate| {
+                candidate.get_long() == Some(key.as_str())
+                    || candidate.get_long() == Some(negation.as_str())
+            })
+            .any(|candidate| {
+                render_matches.value_source(candidate.get_id().as_str())
+                    == Some(ValueSource::CommandLine)
+            });
+        if given {
+            continue;
         }
-        None => {
-            process_all_animations(&args, uid, &mut unit, &src_img, anim_file_type)?;
+        let counted = matches!(arg.get_action(), clap::ArgAction::Count);
+        config_args.extend(
+            config::to_cli_args(key, value, counted)?
+                .into_iter()
+                .map(OsString::from),
+        );
+    }
+
+    // `render` follows the program name and any global flags
+    let render_index = subcommand_index(&args);
+    args.splice(render_index + 1..render_index + 1, config_args);
+    Ok(args)
+}
+
+fn load_character_db() -> ffbetool::Result<character_db::Db> {
+    match character_db::Db::from_file(CHARACTER_DB_FILE) {
+        Ok(db) => Ok(db),
+        Err(_) => {
+            let body = ureq::get(REMOTE_DATA_FILE).call()?.into_string()?;
+            Ok(serde_json::from_str(&body)?)
         }
     }
+}
 
-    Ok(())
+/// Resolves a unit id or character name to a unit id. The character database is only
+/// loaded for names.
+fn resolve_uid(identifier: &UnitIdentifier) -> ffbetool::Result<u32> {
+    let name = match identifier {
+        UnitIdentifier::Id(id) => return Ok(*id),
+        UnitIdentifier::Name(name) => name,
+    };
+
+    match load_character_db()?.find_by_name(name) {
+        character_db::LookupResult::Found(id) => Ok(id),
+        character_db::LookupResult::NotFound => Err(FfbeError::CharacterNotFound(name.to_owned())),
+        character_db::LookupResult::Multiple(similar_matches) => {
+            eprintln!("Did you mean one of the following? Try again with the associated uid.");
+            let message = similar_matches
+                .iter()
+                .map(|(uid, char_info)| format!("{uid} -> {}", char_info.name))
+                .collect::<Vec<String>>()
+                .join("\n\t");
+            eprintln!("\n\t{message}\n\n");
+            Err(FfbeError::CharacterNotFound(name.to_owned()))
+        }
+    }
 }
 
-fn process_single_animation(
-    args: &Args,
-    uid: u32,
-    unit: &mut ffbetool::Unit,
-    src_img: &image::DynamicImage,
-    anim_name: &str,
-    anim_file_type: AnimFileType,
-) -> ffbetool::Result<()> {
-    let mut composite_frames = process_animation_frames(args, uid, unit, src_img, anim_name)?;
+/// Renders the requested animations of a unit, then writes the `--report` file. Fails when
+/// the unit cannot be loaded or any animation failed.
+fn render(args: &Args) -> ffbetool::Result<()> {
+    let start = Instant::now();
+    let mut reporter = Reporter::new(
+        &args.uid.to_string(),
+        args.report_ndjson.as_deref().map(std::path::Path::new),
+    )?;
+
+    let result = render_unit(args, &mut reporter);
+    if let Err(err) = &result {
+        reporter.unit.error = Some(ErrorReport::from(err));
+    }
+    reporter.unit.duration_ms = start.elapsed().as_millis() as u64;
 
-    // Calculate frame bounds and resize empty frames, then crop frames
-    let frame_rect = calculate_frame_rect(unit)?;
-    resize_empty_frames_to_bounds(&mut composite_frames, frame_rect);
-    crop_frames_to_bounds(&mut composite_frames, frame_rect);
+    let unit_report = reporter.finish()?;
+    if let Some(path) = &args.report {
+        RunReport::new(vec![unit_report]).save(std::path::Path::new(path))?;
+    }
+    result
+}
 
-    // Generate outputs
-    save_animated_files(args, uid, anim_name, &composite_frames, anim_file_type)?;
-    let spritesheet = create_spritesheet(&composite_frames, frame_rect, args.columns);
-    save_spritesheet(args, uid, anim_name, &spritesheet)?;
+fn render_unit(args: &Args, reporter: &mut Reporter) -> ffbetool::Result<()> {
+    let uid = resolve_uid(&args.uid)?;
+    reporter.unit.unit_id = Some(uid);
 
-    if args.save_json {
-        save_json_output(
-            args,
-            uid,
-            anim_name,
-            &composite_frames,
-            frame_rect,
-            &spritesheet,
-        )?;
+    validation::validate_input_args(uid, &args.input_dir, single_animation(args))?;
+    if !args.dry_run {
+        validation::validate_output_dir(&args.output_dir)?;
     }
+    validation::validate_scale(args.scale, args.scaler)?;
 
-    println!("Successfully processed animation: {}", anim_name);
-    Ok(())
+    let outputs = unit_output_paths(
+        &args.output_dir,
+        &args.filename,
+        args.overwrite,
+        &args.input_dir,
+        uid,
+    )?;
+
+    let animations = match single_animation(args) {
+        Some(anim_name) => vec![anim_name.to_string()],
+        None => select_animations(args, uid)?,
+    };
+
+    // Load and process frame data
+    let frames = render::load_cgg_frames(uid, &args.input_dir)?;
+    let bars = RenderProgressBars::new(uid);
+    let progress: &dyn ProgressHook = match &bars {
+        Some(bars) => bars,
+        None => &progress::no_progress,
+    };
+    let options = RenderOptions::from(args);
+    let ctx = RenderContext::new(
+        &options,
+        outputs,
+        render::create_unit(uid, frames),
+        ffbetool::imageops::load_source_image(uid, &args.input_dir)?,
+        progress,
+    );
+    let mut manifest = load_manifest(&args.output_dir);
+
+    let result = render::process_animations(&ctx, &animations, &mut manifest, reporter);
+    if args.dry_run {
+        for animation in &reporter.unit.animations {
+            print_output_plan(&animation.outputs, streams_to_stdout(args));
+        }
+    } else {
+        manifest.save(std::path::Path::new(&args.output_dir))?;
+    }
+
+    log::debug!(
+        "part cache: {} distinct parts, {} hits, {} misses",
+        ctx.part_cache.len(),
+        ctx.part_cache.hits(),
+        ctx.part_cache.misses()
+    );
+
+    result
 }
 
-fn process_all_animations(
-    args: &Args,
+/// The output paths of a unit, loading the character database when the template needs it.
+fn unit_output_paths(
+    output_dir: &str,
+    template: &OutputTemplate,
+    overwrite: OverwritePolicy,
+    input_dir: &str,
     uid: u32,
-    unit: &mut ffbetool::Unit,
-    src_img: &image::DynamicImage,
-    anim_file_type: AnimFileType,
-) -> ffbetool::Result<()> {
-    let discovered_animations = discovery::discover_animations(uid, &args.input_dir)?;
+) -> ffbetool::Result<OutputPaths> {
+    let mut outputs = OutputPaths::new(output_dir, template.clone(), uid);
+    outputs.overwrite = overwrite;
+    outputs.input_dir = std::path::PathBuf::from(input_dir);
+    if outputs.template.uses_character_info() {
+        outputs.character = load_character_db()?.get(uid).cloned();
+    }
+    Ok(outputs)
+}
 
-    println!(
+/// Renders, then polls the unit's input files and renders again whenever they change. The
+/// manifest limits each re-render to the animations whose inputs changed.
+fn watch(args: &Args) -> ffbetool::Result<()> {
+    let uid = resolve_uid(&args.uid)?;
+    let mut snapshot = watch::Snapshot::capture(uid, &args.input_dir)?;
+    watch_cycle(args, uid);
+
+    // Only the first run honours --force, later runs re-render what changed
+    let args = Args {
+        force: false,
+        ..args.clone()
+    };
+    log::info!(
+        "\nWatching {} for changes to unit {uid} (press Ctrl+C to stop)",
+        args.input_dir
+    );
+
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        let mut current = watch::Snapshot::capture(uid, &args.input_dir)?;
+        if current == snapshot {
+            continue;
+        }
+
+        // Editors often save in several steps, so wait until the files stop changing
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let next = watch::Snapshot::capture(uid, &args.input_dir)?;
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+
+        log::info!("");
+        for path in current.changed_files(&snapshot) {
+            log::info!("Changed: {}", path.display());
+        }
+        snapshot = current;
+        watch_cycle(&args, uid);
+    }
+}
+
+/// Lints the unit and renders it, reporting failures without stopping the watch.
+fn watch_cycle(args: &Args, uid: u32) {
+    match lint::lint_unit(uid, &args.input_dir) {
+        Ok(issues) => {
+            print_issues(&issues);
+        }
+        Err(err) => log::error!("Lint failed: {err:#}"),
+    }
+
+    let start = Instant::now();
+    match render(args) {
+        Ok(()) => log::info!("Rendered in {:.2?}", start.elapsed()),
+        Err(err) => log::error!("Render failed after {:.2?}: {err:#}", start.elapsed()),
+    }
+}
+
+/// Returns the animation name when exactly one animation is requested by its exact name,
+/// which is rendered directly without discovering the unit's other animations.
+fn single_animation(args: &Args) -> Option<&str> {
+    match args.anim.as_slice() {
+        [name] if !discovery::is_glob(name) && args.exclude.is_empty() => Some(name),
+        _ => None,
+    }
+}
+
+/// Discovers the unit's animations and applies the `--anim` and `--exclude` filters.
+fn select_animations(args: &Args, uid: u32) -> ffbetool::Result<Vec<String>> {
+    let discovered_animations = discovery::filter_animations(
+        discovery::discover_animations(uid, &args.input_dir)?,
+        &args.anim,
+        &args.exclude,
+    );
+
+    for pattern in &args.anim {
+        if !discovered_animations
+            .iter()
+            .any(|animation| discovery::matches_pattern(pattern, &animation.name))
+        {
+            log::warn!("No animation matches '{pattern}'");
+        }
+    }
+    if discovered_animations.is_empty() {
+        return Err(FfbeError::InvalidInput(format!(
+            "No animations of unit {uid} match the requested filters"
+        )));
+    }
+
+    log::info!(
         "Discovered {} animations for unit {}: {}",
         discovered_animations.len(),
         uid,
@@ -198,692 +842,945 @@ fn process_all_animations(
             .join(", ")
     );
 
-    let mut processed_count = 0;
-    let mut failed_animations = Vec::new();
-
-    for animation in discovered_animations {
-        println!("Processing animation: {}", animation.name);
-
-        // Reset unit bounds for each animation
-        let mut unit = unit.clone();
-
-        match process_animation_frames(args, uid, &mut unit, src_img, &animation.name) {
-            Ok(mut composite_frames) => {
-                // Calculate frame bounds and resize empty frames, then crop frames
-                match calculate_frame_rect(&unit) {
-                    Ok(frame_rect) => {
-                        resize_empty_frames_to_bounds(&mut composite_frames, frame_rect);
-                        crop_frames_to_bounds(&mut composite_frames, frame_rect);
-
-                        // Generate outputs
-                        if let Err(err) = save_animated_files(
-                            args,
-                            uid,
-                            &animation.name,
-                            &composite_frames,
-                            anim_file_type,
-                        ) {
-                            eprintln!(
-                                "Failed to save animated files for {}: {}",
-                                animation.name, err
-                            );
-                            failed_animations.push(animation.name.clone());
-                            continue;
-                        }
-
-                        let spritesheet =
-                            create_spritesheet(&composite_frames, frame_rect, args.columns);
-                        if let Err(err) = save_spritesheet(args, uid, &animation.name, &spritesheet)
-                        {
-                            eprintln!("Failed to save spritesheet for {}: {}", animation.name, err);
-                            failed_animations.push(animation.name.clone());
-                            continue;
-                        }
-
-                        if args.save_json
-                            && let Err(err) = save_json_output(
-                                args,
-                                uid,
-                                &animation.name,
-                                &composite_frames,
-                                frame_rect,
-                                &spritesheet,
-                            )
-                        {
-                            eprintln!("Failed to save JSON for {}: {}", animation.name, err);
-                        }
-
-                        processed_count += 1;
-                        println!("✓ Successfully processed: {}", animation.name);
-                    }
-                    Err(err) => {
-                        eprintln!(
-                            "Failed to calculate frame bounds for {}: {}",
-                            animation.name, err
-                        );
-                        failed_animations.push(animation.name);
-                    }
+    Ok(discovered_animations
+        .into_iter()
+        .map(|animation| animation.name)
+        .collect())
+}
+
+/// Progress bars for the animations of a unit and the frames of each animation in progress.
+struct RenderProgressBars {
+    unit: ProgressBar,
+    /// Frame bars of the animations in progress, which may render in parallel.
+    animations: Mutex<HashMap<String, ProgressBar>>,
+    tracker: Mutex<ProgressTracker>,
+}
+
+impl RenderProgressBars {
+    /// Drawn on stderr, so only shown when stderr is a terminal and progress is logged, i.e.
+    /// without `-q`.
+    fn new(uid: u32) -> Option<Self> {
+        if !std::io::stderr().is_terminal() || log::max_level() < log::LevelFilter::Info {
+            return None;
+        }
+
+        let unit = PROGRESS_BARS.add(ProgressBar::new(0).with_style(progress_style(
+            "{prefix:<20} [{bar:30}] {pos}/{len} animations {msg}",
+        )));
+        unit.set_prefix(uid.to_string());
+
+        Some(Self {
+            unit,
+            animations: Mutex::new(HashMap::new()),
+            tracker: Mutex::new(ProgressTracker::new()),
+        })
+    }
+}
+
+fn progress_style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .expect("progress template is valid")
+        .progress_chars("=> ")
+}
+
+impl ProgressHook for RenderProgressBars {
+    fn progress(&self, progress: Progress) {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.update(progress);
+        let mut animations = self.animations.lock().unwrap();
+
+        match progress {
+            Progress::UnitStarted { animations, .. } => self.unit.set_length(animations as u64),
+            Progress::AnimationStarted { name } => {
+                let bar = PROGRESS_BARS.add(
+                    ProgressBar::new(0)
+                        .with_style(progress_style("{prefix:<20} [{bar:30}] {pos}/{len} frames")),
+                );
+                bar.set_prefix(name.to_string());
+                animations.insert(name.to_string(), bar);
+            }
+            Progress::FrameComposited { name, total, .. } => {
+                if let Some(bar) = animations.get(name) {
+                    bar.set_length(total as u64);
+                    bar.inc(1);
+                }
+            }
+            Progress::AnimationFinished { name } => {
+                if let Some(bar) = animations.remove(name) {
+                    bar.finish_and_clear();
+                    PROGRESS_BARS.remove(&bar);
                 }
+                self.unit.inc(1);
             }
-            Err(err) => {
-                eprintln!("Failed to process animation {}: {}", animation.name, err);
-                failed_animations.push(animation.name);
+            Progress::UnitFinished { .. } => {
+                self.unit.finish_and_clear();
+                return;
             }
         }
+
+        if let Some(eta) = tracker.eta() {
+            self.unit
+                .set_message(format!("(ETA {})", HumanDuration(eta)));
+        }
     }
+}
 
-    println!("\nProcessing complete:");
-    println!("✓ Successfully processed: {} animations", processed_count);
+impl Drop for RenderProgressBars {
+    fn drop(&mut self) {
+        for bar in self.animations.get_mut().unwrap().values() {
+            PROGRESS_BARS.remove(bar);
+        }
+        PROGRESS_BARS.remove(&self.unit);
+    }
+}
 
-    if !failed_animations.is_empty() {
-        println!(
-            "✗ Failed to process: {} animations ({})",
-            failed_animations.len(),
-            failed_animations.join(", ")
+/// Prints the plan to stdout, or to stderr when stdout carries the `--report-ndjson -`
+/// stream.
+fn print_output_plan(plan: &[OutputReport], to_stderr: bool) {
+    for output in plan {
+        let size = match (output.width, output.height) {
+            (Some(width), Some(height)) => format!(", {width}x{height}"),
+            _ => String::new(),
+        };
+        let line = format!(
+            "  {:<9} {} ({}{size})",
+            output.action.to_string(),
+            output.path.display(),
+            output.format
         );
+        if to_stderr {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
     }
-
-    Ok(())
 }
 
-fn determine_animation_file_type(args: &Args) -> AnimFileType {
-    match (args.save_gif, args.save_apng) {
-        (true, _) => AnimFileType::Gif,
-        (_, true) => AnimFileType::Apng,
-        _ => AnimFileType::None,
-    }
+/// Whether `--report-ndjson -` streams to stdout, which then carries nothing else.
+fn streams_to_stdout(args: &Args) -> bool {
+    args.report_ndjson.as_deref() == Some("-")
 }
 
-fn load_cgg_frames(unit_id: u32, input_path: &str) -> ffbetool::Result<Vec<cgg::FrameParts>> {
-    println!("ffbetool on {unit_id} cgg-file:[{input_path}]");
+fn load_manifest(output_dir: &str) -> Manifest {
+    Manifest::load(std::path::Path::new(output_dir)).unwrap_or_else(|err| {
+        log::warn!("Ignoring unreadable manifest: {err:#}");
+        Manifest::default()
+    })
+}
 
-    let reader = cgg::read_file(unit_id, input_path).map_err(|err| {
-        eprintln!("failed to process cgg file: {err}");
-        err
-    })?;
+fn info(args: &InfoArgs) -> ffbetool::Result<()> {
+    let uid = resolve_uid(&args.unit.uid)?;
+    validation::validate_input_args(uid, &args.unit.input_dir, None)?;
 
-    let mut frames = Vec::new();
-    for (row, line_result) in reader.lines().enumerate() {
-        let line = line_result.map_err(|err| {
-            eprintln!("Failed to read line {}: {}", row + 1, err);
-            err
-        })?;
+    let mut info = UnitInfo::collect(uid, &args.unit.input_dir)?;
+    info.name = load_character_db()
+        .ok()
+        .and_then(|db| db.get(uid).map(|character| character.name.clone()));
 
-        match cgg::process(&line, row)? {
-            Some(frame_parts) => frames.push(frame_parts),
-            None => continue, // Skip empty lines
-        }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print_info_table(&info);
     }
 
-    Ok(frames)
+    Ok(())
 }
 
-fn create_unit(unit_id: u32, frames: Vec<cgg::FrameParts>) -> ffbetool::Unit {
-    ffbetool::Unit {
-        id: unit_id,
-        frames,
-        ..Default::default()
+fn print_info_table(info: &UnitInfo) {
+    let join = |values: &[i32]| {
+        values
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    println!(
+        "Unit {} ({})",
+        info.unit_id,
+        info.name.as_deref().unwrap_or("unknown")
+    );
+    println!("cgg frames: {} ({} parts)", info.cgg_frames, info.cgg_parts);
+    println!(
+        "Atlas: {}x{}, pages: {}",
+        info.atlas_width,
+        info.atlas_height,
+        info.atlas_pages
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    println!(
+        "\n{:<20} {:>6} {:>10} {:>10}  {:<22} {:<6} Rotations",
+        "Animation", "Frames", "Duration", "cgg frames", "Bounds", "Blend"
+    );
+    for animation in &info.animations {
+        let bounds = animation.bounds.map_or("empty".to_string(), |rect| {
+            format!("{},{} {}x{}", rect.x, rect.y, rect.width, rect.height)
+        });
+        println!(
+            "{:<20} {:>6} {:>7} ms {:>10}  {:<22} {:<6} {}",
+            animation.name,
+            animation.frames,
+            animation.duration_ms,
+            animation.distinct_cgg_frames,
+            bounds,
+            join(&animation.blend_modes),
+            join(&animation.rotations)
+        );
     }
 }
 
-fn process_animation_frames(
-    args: &Args,
-    uid: u32,
-    unit: &mut ffbetool::Unit,
-    src_img: &image::DynamicImage,
-    anim_name: &str,
-) -> ffbetool::Result<Vec<cgs::CompositeFrame>> {
-    let cgs_frames_meta = load_cgs_metadata(uid, anim_name, &args.input_dir)?;
-    let frames = create_cgs_frames(cgs_frames_meta, unit);
-    let composite_frames = process_frames(&frames, src_img, unit, args.include_empty);
-
-    Ok(composite_frames)
-}
-
-fn load_cgs_metadata(
-    unit_id: u32,
-    anim_name: &str,
-    input_path: &str,
-) -> ffbetool::Result<Vec<cgs::CgsMeta>> {
-    let reader = cgs::read_file(unit_id, anim_name, input_path)
-        .map_err(|err| FfbeError::ParseError(format!("failed to process cgs file: {err}")))?;
-
-    let mut cgs_frames_meta = Vec::new();
-    for line_result in reader.lines() {
-        let line = line_result.map_err(|err| {
-            eprintln!("Failed to read cgs line: {err}");
-            err
-        })?;
+fn list(args: &UnitArgs) -> ffbetool::Result<()> {
+    let uid = resolve_uid(&args.uid)?;
+    for animation in discovery::discover_animations(uid, &args.input_dir)? {
+        println!("{}", animation.name);
+    }
+    Ok(())
+}
+
+fn search(args: &SearchArgs) -> ffbetool::Result<()> {
+    let db = load_character_db()?;
+    let mut matches = match db.find_by_name(&args.query) {
+        character_db::LookupResult::Found(uid) => db
+            .get(uid)
+            .map(|info| vec![(uid, info.clone())])
+            .unwrap_or_default(),
+        character_db::LookupResult::Multiple(matches) => matches,
+        character_db::LookupResult::NotFound => {
+            return Err(FfbeError::CharacterNotFound(args.query.clone()));
+        }
+    };
+    matches.sort_by_key(|(uid, _)| *uid);
 
-        match cgs::process(&line) {
-            Some(Ok(meta)) => cgs_frames_meta.push(meta),
-            Some(Err(err)) => {
-                eprintln!("Failed to parse cgs line: {err}");
-                return Err(err);
+    for (uid, info) in matches {
+        match info.rarity.as_deref() {
+            Some(rarity) if !rarity.is_empty() => {
+                println!("{uid} -> {} ({}, {rarity})", info.name, info.r#type)
             }
-            None => continue, // Skip empty lines
+            _ => println!("{uid} -> {} ({})", info.name, info.r#type),
         }
     }
 
-    Ok(cgs_frames_meta)
-}
-
-fn create_cgs_frames(cgs_frames_meta: Vec<cgs::CgsMeta>, unit: &ffbetool::Unit) -> Vec<cgs::Frame> {
-    cgs_frames_meta
-        .into_iter()
-        .map(|meta| {
-            let cgs::CgsMeta(frame_idx, frame_offset_x, frame_offset_y, delay) = meta;
-            let parts = unit.frames[frame_idx].clone();
-            cgs::Frame {
-                frame_idx,
-                parts,
-                offset_x: frame_offset_x,
-                offset_y: frame_offset_y,
-                delay,
-            }
-        })
-        .collect()
-}
-
-fn calculate_frame_rect(unit: &ffbetool::Unit) -> ffbetool::Result<ffbetool::imageops::Rect> {
-    let top_left = unit
-        .top_left
-        .ok_or(FfbeError::MissingValue("top_left".to_string()))?;
-    let bottom_right = unit
-        .bottom_right
-        .ok_or(FfbeError::MissingValue("bottom_right".to_string()))?;
-
-    Ok(ffbetool::imageops::Rect {
-        x: top_left.x(),
-        y: top_left.y(),
-        width: (bottom_right.x() - top_left.x()) as u32 + FRAME_PADDING,
-        height: (bottom_right.y() - top_left.y()) as u32 + FRAME_PADDING,
-    })
+    Ok(())
 }
 
-fn resize_empty_frames_to_bounds(
-    frames: &mut [cgs::CompositeFrame],
-    frame_rect: ffbetool::imageops::Rect,
-) {
-    for frame in frames.iter_mut() {
-        if frame.image.width() == 1 && frame.image.height() == 1 {
-            // Create a new transparent image with full frame dimensions
-            let full_frame = image::RgbaImage::new(frame_rect.width, frame_rect.height);
+fn lint(args: &UnitArgs) -> ffbetool::Result<()> {
+    let uid = resolve_uid(&args.uid)?;
+    let issues = lint::lint_unit(uid, &args.input_dir)?;
 
-            frame.image = full_frame;
-            frame.rect = frame_rect;
-        }
+    let errors = print_issues(&issues);
+    if errors > 0 {
+        return Err(FfbeError::InvalidInput(format!(
+            "unit {uid} has {errors} lint errors"
+        )));
     }
+    Ok(())
 }
 
-fn crop_frames_to_bounds(frames: &mut [cgs::CompositeFrame], frame_rect: ffbetool::imageops::Rect) {
-    frames.iter_mut().for_each(|frame| {
-        // Only crop frames that are larger than the target size
-        if frame.image.width() > frame_rect.width || frame.image.height() > frame_rect.height {
-            frame.image = imageops::crop(
-                &mut frame.image,
-                frame_rect.x as u32,
-                frame_rect.y as u32,
-                frame_rect.width,
-                frame_rect.height,
-            )
-            .to_image();
-        }
-    });
+/// Prints each issue and a summary line, returning the number of errors.
+fn print_issues(issues: &[lint::Issue]) -> usize {
+    for issue in issues {
+        println!("{issue}");
+    }
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == lint::Severity::Error)
+        .count();
+    println!("\n{} errors, {} warnings", errors, issues.len() - errors);
+    errors
 }
 
-fn save_animated_files(
-    args: &Args,
-    uid: u32,
-    anim_name: &str,
-    frames: &[cgs::CompositeFrame],
-    anim_file_type: AnimFileType,
-) -> ffbetool::Result<()> {
-    match anim_file_type {
-        AnimFileType::Apng => {
-            let output_path = format!("{}/{}-{}-anim.png", args.output_dir, uid, anim_name);
-            ffbetool::imageops::encode_animated_apng(frames.to_vec(), &output_path)?;
-        }
-        AnimFileType::Gif => {
-            let output_path = format!("{}/{}-{}-anim.gif", args.output_dir, uid, anim_name);
-            ffbetool::imageops::encode_animated_gif(frames.to_vec(), &output_path)?;
-        }
-        AnimFileType::None => {}
-    }
+fn update_db(output: &str) -> ffbetool::Result<()> {
+    let body = ureq::get(REMOTE_DATA_FILE).call()?.into_string()?;
+    // Make sure the download is a valid database before replacing the local copy
+    let db: character_db::Db = serde_json::from_str(&body)?;
+    std::fs::write(output, body)?;
+
+    log::info!("Saved {} characters to {output}", db.iter().len());
     Ok(())
 }
 
-fn create_spritesheet(
-    frames: &[cgs::CompositeFrame],
-    frame_rect: ffbetool::imageops::Rect,
-    columns: usize,
-) -> image::RgbaImage {
-    if columns == 0 || columns >= frames.len() {
-        create_single_row_spritesheet(frames, frame_rect)
+/// Loads the unit's cgg frames and atlas for the atlas commands.
+fn load_atlas_unit(
+    args: &UnitArgs,
+) -> ffbetool::Result<(u32, ffbetool::Unit, image::DynamicImage)> {
+    let uid = resolve_uid(&args.uid)?;
+    validation::validate_input_args(uid, &args.input_dir, None)?;
+
+    let unit = render::create_unit(uid, render::load_cgg_frames(uid, &args.input_dir)?);
+    let src_img = ffbetool::imageops::load_source_image(uid, &args.input_dir)?;
+    Ok((uid, unit, src_img))
+}
+
+fn save_atlas_map(args: &AtlasArgs) -> ffbetool::Result<()> {
+    let (uid, unit, src_img) = load_atlas_unit(&args.unit)?;
+    let outputs = atlas_output_paths(args, uid)?;
+    let (unit, src_img) = (&unit, &src_img);
+    let input_dir = &args.unit.input_dir;
+
+    let animations = discovery::discover_animations(uid, input_dir)?
+        .into_iter()
+        .map(|animation| {
+            let frame_indices = render::load_cgs_metadata(uid, &animation.name, input_dir)?
+                .iter()
+                .map(|(_, cgs::CgsMeta(frame_idx, ..))| *frame_idx)
+                .collect();
+            Ok(atlas::AnimationFrames {
+                name: animation.name,
+                frame_indices,
+            })
+        })
+        .collect::<ffbetool::Result<Vec<_>>>()?;
+
+    let report = atlas::AtlasReport::new(unit, src_img, &animations);
+    if args.dry_run {
+        let mut planned = vec![
+            PlannedOutput::new(
+                &outputs,
+                ATLAS_MAP_NAME,
+                OutputKind::AtlasMap,
+                Some((src_img.width(), src_img.height())),
+            )
+            .report(false),
+        ];
+        if args.save_json {
+            planned.push(
+                PlannedOutput::new(&outputs, ATLAS_MAP_NAME, OutputKind::Json, None).report(false),
+            );
+        }
+        print_output_plan(&planned, false);
     } else {
-        create_multi_row_spritesheet(frames, frame_rect, columns)
+        save_atlas_map_files(args, &outputs, src_img, &report)?;
     }
-}
 
-fn create_single_row_spritesheet(
-    frames: &[cgs::CompositeFrame],
-    frame_rect: ffbetool::imageops::Rect,
-) -> image::RgbaImage {
-    let mut sheet =
-        image::RgbaImage::new(frame_rect.width * (frames.len() as u32), frame_rect.height);
+    print_atlas_summary(&report);
+    Ok(())
+}
 
-    for (idx, frame) in frames.iter().enumerate() {
-        let x = (idx as u32) * frame_rect.width;
-        imageops::overlay(&mut sheet, &frame.image, x as i64, 0);
+fn save_atlas_map_files(
+    args: &AtlasArgs,
+    outputs: &OutputPaths,
+    src_img: &image::DynamicImage,
+    report: &atlas::AtlasReport,
+) -> ffbetool::Result<()> {
+    let outcome = outputs.write(ATLAS_MAP_NAME, OutputKind::AtlasMap, |path| {
+        Ok(atlas::render_map(src_img, report).save(path)?)
+    })?;
+    if let WriteOutcome::Written(map_path) = &outcome {
+        log::info!("Saved atlas map: {}", map_path.display());
     }
+    report_write(outcome);
 
-    sheet
+    if args.save_json {
+        let outcome = outputs.write(ATLAS_MAP_NAME, OutputKind::Json, |path| {
+            Ok(std::fs::write(path, serde_json::to_string_pretty(report)?)?)
+        })?;
+        if let WriteOutcome::Written(json_path) = &outcome {
+            log::info!("Saved atlas report: {}", json_path.display());
+        }
+        report_write(outcome);
+    }
+    Ok(())
 }
 
-fn create_multi_row_spritesheet(
-    frames: &[cgs::CompositeFrame],
-    frame_rect: ffbetool::imageops::Rect,
-    columns: usize,
-) -> image::RgbaImage {
-    let rows = (frames.len() as f32 / columns as f32).ceil() as u32;
-    let mut sheet = image::RgbaImage::new(
-        frame_rect.width * (columns as u32),
-        frame_rect.height * rows,
+fn print_atlas_summary(report: &atlas::AtlasReport) {
+    println!("\nAnimation colours:");
+    for (idx, name) in report.animations.iter().enumerate() {
+        let [r, g, b, _] = atlas::animation_color(idx).0;
+        println!("    {name:<20} #{r:02x}{g:02x}{b:02x}");
+    }
+    println!("    {:<20} #ffffff", "(shared)");
+    println!("    {:<20} #808080", "(no animation)");
+    println!("    {:<20} #ff0000", "(unused)");
+
+    let unanimated = report
+        .regions
+        .iter()
+        .filter(|region| region.animations.is_empty())
+        .count();
+    println!(
+        "\nReferenced atlas regions: {} ({} used by no animation)",
+        report.regions.len(),
+        unanimated
     );
 
-    for (idx, frame) in frames.iter().enumerate() {
-        let x = ((idx % columns) as u32) * frame_rect.width;
-        let y = ((idx / columns) as u32) * frame_rect.height;
-        imageops::overlay(&mut sheet, &frame.image, x as i64, y as i64);
+    println!("Unused atlas regions: {}", report.unused_regions.len());
+    for rect in &report.unused_regions {
+        println!(
+            "    x={} y={} {}x{}",
+            rect.x, rect.y, rect.width, rect.height
+        );
     }
 
-    sheet
+    println!(
+        "Unreferenced cgg frames: {}",
+        report.unreferenced_frames.len()
+    );
+    for frame in &report.unreferenced_frames {
+        println!("    frame {} (cgg line {})", frame.frame_idx, frame.line);
+    }
 }
 
-fn save_json_output(
-    args: &Args,
-    uid: u32,
-    anim_name: &str,
-    frames: &[cgs::CompositeFrame],
-    frame_rect: ffbetool::imageops::Rect,
-    spritesheet: &image::RgbaImage,
-) -> ffbetool::Result<()> {
-    let animation_json = metadata::AnimationJson::from_frames(
+/// The output paths of an atlas command, whose outputs are named like animations called
+/// [`ATLAS_MAP_NAME`] or [`SLICES_NAME`].
+fn atlas_output_paths(args: &AtlasArgs, uid: u32) -> ffbetool::Result<OutputPaths> {
+    if !args.dry_run {
+        validation::validate_output_dir(&args.output_dir)?;
+    }
+    unit_output_paths(
+        &args.output_dir,
+        &args.filename,
+        args.overwrite,
+        &args.unit.input_dir,
         uid,
-        anim_name.to_string(),
-        frames,
-        frame_rect,
-        spritesheet.width(),
-        spritesheet.height(),
-    );
-
-    let output_path = format!("{}/{}-{}.json", args.output_dir, uid, anim_name);
-    metadata::save_animation_json(&animation_json, &output_path)?;
-    Ok(())
+    )
 }
 
-fn save_spritesheet(
-    args: &Args,
-    uid: u32,
-    anim_name: &str,
-    spritesheet: &image::RgbaImage,
-) -> ffbetool::Result<()> {
-    let output_path = format!("{}/{}-{}.png", args.output_dir, uid, anim_name);
-    spritesheet.save(output_path)?;
+fn save_atlas_slices(args: &AtlasArgs) -> ffbetool::Result<()> {
+    let (uid, unit, src_img) = load_atlas_unit(&args.unit)?;
+    let outputs = atlas_output_paths(args, uid)?;
+
+    let index = atlas::SliceIndex::new(&unit, (src_img.width(), src_img.height()));
+    if args.dry_run {
+        print_output_plan(
+            &[PlannedOutput::new(&outputs, SLICES_NAME, OutputKind::Slices, None).report(false)],
+            false,
+        );
+        return Ok(());
+    }
+
+    let outcome = outputs.write(SLICES_NAME, OutputKind::Slices, |slices_dir| {
+        index.save(&src_img, slices_dir)
+    })?;
+    if let WriteOutcome::Written(slices_dir) = &outcome {
+        log::info!(
+            "Saved {} atlas slices to {}",
+            index.slices.len(),
+            slices_dir.display()
+        );
+    }
+    report_write(outcome);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ffbetool::{output::OutputAction, report::AnimationStatus};
     use tempfile::TempDir;
 
     #[test]
-    fn test_determine_animation_file_type() {
-        let args_gif = Args {
-            uid: UnitIdentifier::Id(123),
-            anim: Some("test".to_string()),
-            columns: 0,
-            include_empty: false,
-            verbose: false,
-            save_json: false,
-            save_gif: true,
-            save_apng: false,
-            input_dir: ".".to_string(),
-            output_dir: ".".to_string(),
-        };
+    fn test_with_default_subcommand() {
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            with_default_subcommand(args(&["ffbetool", "123", "-a", "atk"])),
+            args(&["ffbetool", "render", "123", "-a", "atk"])
+        );
+        assert_eq!(
+            with_default_subcommand(args(&["ffbetool", "-a", "atk", "Rain"])),
+            args(&["ffbetool", "render", "-a", "atk", "Rain"])
+        );
+        for unchanged in [
+            &["ffbetool", "info", "123"][..],
+            &["ffbetool", "atlas-map", "123"],
+            &["ffbetool", "help"],
+            &["ffbetool", "--help"],
+            &["ffbetool"],
+        ] {
+            assert_eq!(with_default_subcommand(args(unchanged)), args(unchanged));
+        }
+
+        let cli = Cli::parse_from(with_default_subcommand(args(&["ffbetool", "123"])));
+        assert!(matches!(cli.command, Command::Render(_)));
+
+        // Global flags may come before the subcommand, `--config` belongs to `render`
+        assert_eq!(
+            with_default_subcommand(args(&[
+                "ffbetool",
+                "-vv",
+                "--config",
+                "ffbetool.toml",
+                "123"
+            ])),
+            args(&[
+                "ffbetool",
+                "render",
+                "-vv",
+                "--config",
+                "ffbetool.toml",
+                "123"
+            ])
+        );
+        for unchanged in [
+            &["ffbetool", "-q", "list", "123"][..],
+            &["ffbetool", "-v", "info", "123"],
+            &["ffbetool", "--verbose", "-q", "help"],
+        ] {
+            assert_eq!(with_default_subcommand(args(unchanged)), args(unchanged));
+        }
+    }
 
-        let args_apng = Args {
-            save_gif: false,
-            save_apng: true,
-            ..args_gif.clone()
+    #[test]
+    fn test_global_flags_before_subcommand() {
+        let parse = |list: &[&str]| {
+            let args = with_default_subcommand(list.iter().map(OsString::from).collect());
+            Cli::try_parse_from(with_config_defaults(args).unwrap()).unwrap()
         };
 
-        let args_none = Args {
-            save_gif: false,
-            save_apng: false,
-            ..args_gif.clone()
+        let cli = parse(&["ffbetool", "-q", "list", "204000103", "-i", "test_data"]);
+        assert!(matches!(cli.command, Command::List(_)));
+        assert_eq!(log_level(cli.quiet, cli.verbose), log::LevelFilter::Error);
+
+        let cli = parse(&["ffbetool", "-v", "info", "204000103", "-i", "test_data"]);
+        assert!(matches!(cli.command, Command::Info(_)));
+        assert_eq!(log_level(cli.quiet, cli.verbose), log::LevelFilter::Debug);
+
+        // Without the config, which is only read for `render`
+        let args = ["ffbetool", "-vv", "204000103", "-a", "atk"].map(OsString::from);
+        let cli = Cli::parse_from(with_default_subcommand(args.to_vec()));
+        assert!(matches!(&cli.command, Command::Render(args) if args.anim == ["atk"]));
+        assert_eq!(log_level(cli.quiet, cli.verbose), log::LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_log_level() {
+        let level = |args: &[&str]| {
+            let cli = Cli::parse_from(["ffbetool", "render", "123"].iter().chain(args));
+            log_level(cli.quiet, cli.verbose)
         };
 
-        assert!(matches!(
-            determine_animation_file_type(&args_gif),
-            AnimFileType::Gif
-        ));
-        assert!(matches!(
-            determine_animation_file_type(&args_apng),
-            AnimFileType::Apng
-        ));
-        assert!(matches!(
-            determine_animation_file_type(&args_none),
-            AnimFileType::None
-        ));
+        assert_eq!(level(&[]), log::LevelFilter::Info);
+        assert_eq!(level(&["-q"]), log::LevelFilter::Error);
+        assert_eq!(level(&["-v"]), log::LevelFilter::Debug);
+        assert_eq!(level(&["-vv"]), log::LevelFilter::Trace);
+        assert!(Cli::try_parse_from(["ffbetool", "render", "123", "-q", "-v"]).is_err());
     }
 
     #[test]
-    fn test_create_unit() {
-        let frames = vec![vec![]]; // Empty frame parts
-        let unit = create_unit(12345, frames);
+    fn test_apply_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [defaults]
+            output = "out"
+            json = true
+            columns = 4
+
+            [profiles.web]
+            apng = true
+            scale = 2
+
+            [units.123]
+            anim = ["limit_*"]
+            "#,
+        )
+        .unwrap();
+        let parse = |cli_args: &[&str]| {
+            let args: Vec<OsString> = ["ffbetool", "render"]
+                .iter()
+                .chain(cli_args)
+                .map(OsString::from)
+                .collect();
+            let matches = Cli::command().get_matches_from(&args);
+            let (_, render_matches) = matches.subcommand().unwrap();
+            let args = apply_config(args.clone(), render_matches, &config)?;
+            match Cli::parse_from(args).command {
+                Command::Render(args) => Ok::<_, FfbeError>(args),
+                _ => unreachable!(),
+            }
+        };
+
+        // Command line flags win over the config
+        let args = parse(&["123", "-c", "2", "--profile", "web"]).unwrap();
+        assert_eq!(args.columns, 2);
+        assert_eq!(args.output_dir, "out");
+        assert!(args.save_json && args.save_apng);
+        assert_eq!(args.scale, 2);
+        assert_eq!(args.anim, vec!["limit_*"]);
+
+        // Unit overrides only apply to their unit, profiles only when selected
+        let args = parse(&["456", "-a", "atk"]).unwrap();
+        assert_eq!(args.columns, 4);
+        assert_eq!(args.anim, vec!["atk"]);
+        assert!(!args.save_apng);
 
-        assert_eq!(unit.id, 12345);
-        assert_eq!(unit.frames.len(), 1);
-        assert!(unit.top_left.is_none());
-        assert!(unit.bottom_right.is_none());
+        assert!(parse(&["123", "--profile", "godot"]).is_err());
+
+        // Config options follow `render` when global flags come first
+        let args: Vec<OsString> = ["ffbetool", "-q", "render", "456"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let matches = Cli::command().get_matches_from(&args);
+        let (_, render_matches) = matches.subcommand().unwrap();
+        let cli = Cli::parse_from(apply_config(args.clone(), render_matches, &config).unwrap());
+        assert!(cli.quiet);
+        assert!(matches!(cli.command, Command::Render(args) if args.columns == 4));
     }
 
     #[test]
-    fn test_calculate_frame_rect() {
-        let unit = ffbetool::Unit {
-            id: 123,
-            frames: vec![],
-            top_left: Some(ffbetool::imageops::Point::new(10, 20)),
-            bottom_right: Some(ffbetool::imageops::Point::new(110, 220)),
-            width: None,
-            height: None,
-            x_offset: None,
-            y_offset: None,
+    fn test_apply_config_flags_and_counts() {
+        let parse = |config: &str, cli_args: &[&str]| {
+            let config: Config = toml::from_str(config).unwrap();
+            let args: Vec<OsString> = ["ffbetool", "render", "123"]
+                .iter()
+                .chain(cli_args)
+                .map(OsString::from)
+                .collect();
+            let matches = Cli::command().get_matches_from(&args);
+            let (_, render_matches) = matches.subcommand().unwrap();
+            Cli::parse_from(apply_config(args.clone(), render_matches, &config).unwrap())
         };
+        let render_args = |cli: Cli| match cli.command {
+            Command::Render(args) => args,
+            _ => unreachable!(),
+        };
+
+        // Flags set in the config are turned off with their `--no-` form
+        let config = "[defaults]\njson = true\ngif = true\nverbose = 2\n";
+        let cli = parse(config, &["--no-json"]);
+        assert_eq!(cli.verbose, 2);
+        let args = render_args(cli);
+        assert!(!args.save_json && args.save_gif);
+
+        // On the command line the last of a flag and its `--no-` form wins
+        let args = render_args(parse(config, &["--no-gif", "--gif", "--json", "--no-json"]));
+        assert!(args.save_gif && !args.save_json);
 
-        let rect = calculate_frame_rect(&unit).unwrap();
-        assert_eq!(rect.x, 10);
-        assert_eq!(rect.y, 20);
-        assert_eq!(rect.width, 100 + FRAME_PADDING);
-        assert_eq!(rect.height, 200 + FRAME_PADDING);
+        let cli = parse("[defaults]\nquiet = true\n", &["--no-quiet"]);
+        assert!(!cli.quiet);
     }
 
     #[test]
-    fn test_calculate_frame_rect_missing_bounds() {
-        let unit = ffbetool::Unit::default();
-        let result = calculate_frame_rect(&unit);
-        assert!(result.is_err());
+    fn test_apply_config_rejects_unknown_options() {
+        let config: Config = toml::from_str(
+            "[defaults]
+column = 4
+",
+        )
+        .unwrap();
+        let args: Vec<OsString> = ["ffbetool", "render", "123"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let matches = Cli::command().get_matches_from(&args);
+        let (_, render_matches) = matches.subcommand().unwrap();
+
+        let result = apply_config(args.clone(), render_matches, &config);
         assert!(
             result
                 .unwrap_err()
                 .to_string()
-                .contains("Missing value: top_left")
+                .contains("Unknown option 'column'")
         );
     }
 
     #[test]
-    fn test_create_single_row_spritesheet() {
-        let frames = vec![
-            cgs::CompositeFrame {
-                frame_idx: 0,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-            cgs::CompositeFrame {
-                frame_idx: 1,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-        ];
-
-        let frame_rect = ffbetool::imageops::Rect {
-            x: 0,
-            y: 0,
-            width: 50,
-            height: 50,
-        };
-        let sheet = create_single_row_spritesheet(&frames, frame_rect);
-
-        assert_eq!(sheet.width(), 100); // 2 frames * 50 width
-        assert_eq!(sheet.height(), 50);
+    fn test_single_animation() {
+        let args =
+            |extra: &[&str]| Args::parse_from(["ffbetool", "123"].iter().chain(extra).copied());
+
+        assert_eq!(single_animation(&args(&["-a", "atk"])), Some("atk"));
+        assert_eq!(single_animation(&args(&[])), None);
+        assert_eq!(single_animation(&args(&["-a", "limit_*"])), None);
+        assert_eq!(single_animation(&args(&["-a", "atk", "-a", "idle"])), None);
+        assert_eq!(single_animation(&args(&["-a", "atk,idle"])), None);
+        assert_eq!(
+            single_animation(&args(&["-a", "atk", "--exclude", "idle"])),
+            None
+        );
+        assert_eq!(
+            args(&["-a", "atk,idle", "-a", "move"]).anim,
+            vec!["atk", "idle", "move"]
+        );
     }
 
     #[test]
-    fn test_create_multi_row_spritesheet() {
-        let frames = vec![
-            cgs::CompositeFrame {
-                frame_idx: 0,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-            cgs::CompositeFrame {
-                frame_idx: 1,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-            cgs::CompositeFrame {
-                frame_idx: 2,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-        ];
-
-        let frame_rect = ffbetool::imageops::Rect {
-            x: 0,
-            y: 0,
-            width: 50,
-            height: 50,
+    fn test_dry_run_lists_unchanged_animations() {
+        let output_dir = TempDir::new().unwrap();
+        let render_with = |extra: &[&str]| {
+            let args = Args::parse_from(
+                [
+                    "ffbetool",
+                    "204000103",
+                    "-i",
+                    "test_data",
+                    "-o",
+                    output_dir.path().to_str().unwrap(),
+                    "--json",
+                ]
+                .iter()
+                .chain(extra),
+            );
+            let mut reporter = Reporter::new("204000103", None).unwrap();
+            render_unit(&args, &mut reporter).unwrap();
+            reporter.unit
         };
-        let sheet = create_multi_row_spritesheet(&frames, frame_rect, 2);
 
-        assert_eq!(sheet.width(), 100); // 2 columns * 50 width
-        assert_eq!(sheet.height(), 100); // 2 rows * 50 height (3 frames, 2 columns = 2 rows)
-    }
+        render_with(&[]);
+        let unit = render_with(&["--dry-run"]);
 
-    #[test]
-    fn test_load_cgg_frames_nonexistent() {
-        let result = load_cgg_frames(99999, "nonexistent_path");
-        assert!(result.is_err());
+        assert_eq!(unit.count(AnimationStatus::Skipped), 1);
+        assert_eq!(unit.count(AnimationStatus::Planned), 0);
+        let outputs = &unit.animations[0].outputs;
+        assert_eq!(outputs.len(), 2);
+        assert!(
+            outputs
+                .iter()
+                .all(|output| output.action == OutputAction::Skip)
+        );
+
+        let unit = render_with(&["--dry-run", "--force"]);
+        assert_eq!(unit.count(AnimationStatus::Planned), 1);
     }
 
     #[test]
-    fn test_load_cgg_frames_existing() {
-        let result = load_cgg_frames(204000103, "test_data");
-        assert!(result.is_ok());
+    fn test_overwrite_conflict_writes_nothing() {
+        let output_dir = TempDir::new().unwrap();
+        let render_with = |extra: &[&str]| {
+            let args = Args::parse_from(
+                [
+                    "ffbetool",
+                    "204000103",
+                    "-i",
+                    "test_data",
+                    "-o",
+                    output_dir.path().to_str().unwrap(),
+                ]
+                .iter()
+                .chain(extra),
+            );
+            let mut reporter = Reporter::new("204000103", None).unwrap();
+            let result = render_unit(&args, &mut reporter);
+            (result, reporter.unit)
+        };
 
-        let frames = result.unwrap();
-        assert!(!frames.is_empty());
+        render_with(&[]).0.unwrap();
+        let (result, unit) = render_with(&["--force", "--gif", "--overwrite", "error"]);
+
+        assert!(result.is_err());
+        assert_eq!(unit.count(AnimationStatus::Failed), 1);
+        assert!(unit.animations[0].outputs.is_empty());
+        assert!(!output_dir.path().join("204000103-atk-anim.gif").exists());
     }
 
     #[test]
-    fn test_save_spritesheet() {
-        let temp_dir = TempDir::new().unwrap();
-        let temp_path = temp_dir.path().to_str().unwrap();
-
-        let args = Args {
-            uid: UnitIdentifier::Id(123),
-            anim: Some("test".to_string()),
-            columns: 0,
-            include_empty: false,
-            verbose: false,
-            save_json: false,
-            save_gif: false,
-            save_apng: false,
-            input_dir: ".".to_string(),
-            output_dir: temp_path.to_string(),
+    fn test_save_atlas_map_follows_overwrite_policy() {
+        let output_dir = TempDir::new().unwrap();
+        let map_with = |extra: &[&str]| {
+            let cli = Cli::parse_from(
+                [
+                    "ffbetool",
+                    "atlas-map",
+                    "204000103",
+                    "-i",
+                    "test_data",
+                    "-o",
+                    output_dir.path().to_str().unwrap(),
+                    "--json",
+                ]
+                .iter()
+                .chain(extra),
+            );
+            let Command::AtlasMap(args) = cli.command else {
+                unreachable!()
+            };
+            save_atlas_map(&args)
         };
+        let map_path = output_dir.path().join("204000103-atlas-map.png");
+        let json_path = output_dir.path().join("204000103-atlas-map.json");
 
-        let spritesheet = image::RgbaImage::new(100, 100);
-        let result = save_spritesheet(&args, 123, "test", &spritesheet);
+        map_with(&["--dry-run"]).unwrap();
+        assert!(!map_path.exists() && !json_path.exists());
 
-        assert!(result.is_ok());
+        map_with(&[]).unwrap();
+        assert!(map_path.exists() && json_path.exists());
 
-        let expected_path = format!("{}/123-test.png", temp_path);
-        assert!(std::path::Path::new(&expected_path).exists());
+        std::fs::write(&map_path, "kept").unwrap();
+        map_with(&["--overwrite", "never"]).unwrap();
+        assert_eq!(std::fs::read(&map_path).unwrap(), b"kept");
+        assert!(map_with(&["--overwrite", "error"]).is_err());
+
+        map_with(&["--filename", "{uid}/{anim}.{ext}"]).unwrap();
+        assert!(output_dir.path().join("204000103/atlas-map.png").exists());
+        assert!(output_dir.path().join("204000103/atlas-map.json").exists());
     }
 
     #[test]
-    fn test_resize_empty_frames_to_bounds() {
-        let mut frames = vec![
-            cgs::CompositeFrame {
-                frame_idx: 0,
-                image: image::RgbaImage::new(1, 1), // Empty frame (1x1)
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 1,
-                    height: 1,
-                },
-                delay: 100,
-            },
-            cgs::CompositeFrame {
-                frame_idx: 1,
-                image: image::RgbaImage::new(50, 50), // Normal frame
-                rect: ffbetool::imageops::Rect {
-                    x: 0,
-                    y: 0,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-        ];
-
-        let frame_rect = ffbetool::imageops::Rect {
-            x: 0,
-            y: 0,
-            width: 50,
-            height: 50,
+    fn test_save_atlas_slices_follows_overwrite_policy() {
+        let output_dir = TempDir::new().unwrap();
+        let slice_with = |extra: &[&str]| {
+            let cli = Cli::parse_from(
+                [
+                    "ffbetool",
+                    "slice",
+                    "204000103",
+                    "-i",
+                    "test_data",
+                    "-o",
+                    output_dir.path().to_str().unwrap(),
+                ]
+                .iter()
+                .chain(extra),
+            );
+            let Command::Slice(args) = cli.command else {
+                unreachable!()
+            };
+            save_atlas_slices(&args)
         };
-        resize_empty_frames_to_bounds(&mut frames, frame_rect);
+        let slices_dir = output_dir.path().join("204000103-slices");
 
-        // Empty frame should now be resized to full dimensions
-        assert_eq!(frames[0].image.width(), 50);
-        assert_eq!(frames[0].image.height(), 50);
-        assert_eq!(frames[0].rect.width, 50);
-        assert_eq!(frames[0].rect.height, 50);
+        slice_with(&["--dry-run"]).unwrap();
+        assert!(!slices_dir.exists());
 
-        // Normal frame should remain unchanged
-        assert_eq!(frames[1].image.width(), 50);
-        assert_eq!(frames[1].image.height(), 50);
+        slice_with(&[]).unwrap();
+        assert!(slices_dir.join("index.json").exists());
+
+        std::fs::remove_file(slices_dir.join("index.json")).unwrap();
+        slice_with(&["--overwrite", "never"]).unwrap();
+        assert!(!slices_dir.join("index.json").exists());
+        assert!(slice_with(&["--overwrite", "error"]).is_err());
+
+        slice_with(&["--filename", "{anim}/{uid}"]).unwrap();
+        assert!(
+            output_dir
+                .path()
+                .join("slices/204000103/index.json")
+                .exists()
+        );
     }
 
     #[test]
-    fn test_save_json_output() {
-        let temp_dir = TempDir::new().unwrap();
-        let temp_path = temp_dir.path().to_str().unwrap();
-
-        let args = Args {
-            uid: UnitIdentifier::Id(123),
-            anim: Some("test".to_string()),
-            columns: 0,
-            include_empty: false,
-            verbose: false,
-            save_json: true,
-            save_gif: false,
-            save_apng: false,
-            input_dir: ".".to_string(),
-            output_dir: temp_path.to_string(),
+    fn test_render_jobs_match_sequential_render() {
+        let input_dir = TempDir::new().unwrap();
+        for file in [
+            "unit_anime_204000103.png",
+            "unit_cgg_204000103.csv",
+            "unit_atk_cgs_204000103.csv",
+        ] {
+            std::fs::copy(
+                std::path::Path::new("test_data").join(file),
+                input_dir.path().join(file),
+            )
+            .unwrap();
+        }
+        std::fs::write(
+            input_dir.path().join("unit_move_cgs_204000103.csv"),
+            "0,0,0,2\n1,4,0,2\n",
+        )
+        .unwrap();
+        std::fs::write(input_dir.path().join("unit_bad_cgs_204000103.csv"), "x\n").unwrap();
+
+        let render_with = |jobs: &str| {
+            let output_dir = TempDir::new().unwrap();
+            let args = Args::parse_from([
+                "ffbetool",
+                "204000103",
+                "-i",
+                input_dir.path().to_str().unwrap(),
+                "-o",
+                output_dir.path().to_str().unwrap(),
+                "--json",
+                "--jobs",
+                jobs,
+            ]);
+            let mut reporter = Reporter::new("204000103", None).unwrap();
+            let result = render_unit(&args, &mut reporter);
+            (output_dir, result, reporter.unit)
         };
 
-        let frames = vec![
-            cgs::CompositeFrame {
-                frame_idx: 0,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 10,
-                    y: 20,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 100,
-            },
-            cgs::CompositeFrame {
-                frame_idx: 1,
-                image: image::RgbaImage::new(50, 50),
-                rect: ffbetool::imageops::Rect {
-                    x: 10,
-                    y: 20,
-                    width: 50,
-                    height: 50,
-                },
-                delay: 150,
-            },
-        ];
+        let (sequential_dir, sequential_result, _) = render_with("1");
+        let (parallel_dir, parallel_result, unit) = render_with("3");
+
+        // The broken animation fails the run without stopping the others
+        for result in [sequential_result, parallel_result] {
+            assert!(
+                result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("1 of 3 animations")
+            );
+        }
+        assert_eq!(unit.count(AnimationStatus::Rendered), 2);
+        assert_eq!(unit.count(AnimationStatus::Failed), 1);
+
+        for file in [
+            "204000103-atk.png",
+            "204000103-atk.json",
+            "204000103-move.png",
+            "204000103-move.json",
+        ] {
+            assert_eq!(
+                std::fs::read(sequential_dir.path().join(file)).unwrap(),
+                std::fs::read(parallel_dir.path().join(file)).unwrap(),
+                "{file}"
+            );
+        }
+    }
 
-        let frame_rect = ffbetool::imageops::Rect {
-            x: 5,
-            y: 10,
-            width: 60,
-            height: 70,
-        };
-        let spritesheet = image::RgbaImage::new(120, 70);
-
-        let result = save_json_output(&args, 123, "test_anim", &frames, frame_rect, &spritesheet);
-        assert!(result.is_ok());
-
-        let expected_path = format!("{}/123-test_anim.json", temp_path);
-        assert!(std::path::Path::new(&expected_path).exists());
-
-        // Parse and validate JSON structure
-        let json_content = std::fs::read_to_string(&expected_path).unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&json_content).unwrap();
-
-        // Validate all required fields exist and have correct values
-        assert_eq!(parsed["unitId"], 123);
-        assert_eq!(parsed["animName"], "test_anim");
-        assert_eq!(parsed["frameDelays"], serde_json::json!([100, 150]));
-        assert_eq!(parsed["frameRect"]["x"], 5);
-        assert_eq!(parsed["frameRect"]["y"], 10);
-        assert_eq!(parsed["frameRect"]["width"], 60);
-        assert_eq!(parsed["frameRect"]["height"], 70);
-        assert_eq!(parsed["imageWidth"], 120);
-        assert_eq!(parsed["imageHeight"], 70);
-
-        // Ensure no extra fields
-        let expected_keys = [
-            "unitId",
-            "animName",
-            "frameDelays",
-            "frameRect",
-            "imageWidth",
-            "imageHeight",
-        ];
-        assert_eq!(parsed.as_object().unwrap().len(), expected_keys.len());
-        for key in expected_keys {
-            assert!(parsed.as_object().unwrap().contains_key(key));
+    #[test]
+    fn test_render_reports_missing_cgg_frame() {
+        let input_dir = TempDir::new().unwrap();
+        for file in [
+            "unit_anime_204000103.png",
+            "unit_cgg_204000103.csv",
+            "unit_atk_cgs_204000103.csv",
+        ] {
+            std::fs::copy(
+                std::path::Path::new("test_data").join(file),
+                input_dir.path().join(file),
+            )
+            .unwrap();
         }
+        std::fs::write(
+            input_dir.path().join("unit_far_cgs_204000103.csv"),
+            "0,0,0,2\n9999,0,0,2\n",
+        )
+        .unwrap();
+        let output_dir = TempDir::new().unwrap();
+        let args = Args::parse_from([
+            "ffbetool",
+            "204000103",
+            "-i",
+            input_dir.path().to_str().unwrap(),
+            "-o",
+            output_dir.path().to_str().unwrap(),
+            "--layers",
+            "--debug-overlay",
+            "--jobs",
+            "2",
+        ]);
+
+        let mut reporter = Reporter::new("204000103", None).unwrap();
+        assert!(render_unit(&args, &mut reporter).is_err());
+
+        let unit = reporter.unit;
+        assert_eq!(unit.count(AnimationStatus::Rendered), 1);
+        let far = unit
+            .animations
+            .iter()
+            .find(|animation| animation.name == "far")
+            .unwrap();
+        assert_eq!(far.status, AnimationStatus::Failed);
+        let error = far.error.as_ref().unwrap();
+        assert_eq!(error.line, Some(2));
+        assert!(
+            error
+                .path
+                .as_ref()
+                .unwrap()
+                .ends_with("unit_far_cgs_204000103.csv")
+        );
+        assert!(error.message.contains("cgg frame 9999"));
+        assert!(
+            output_dir
+                .path()
+                .join(ffbetool::manifest::MANIFEST_FILE)
+                .exists()
+        );
     }
 }
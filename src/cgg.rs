@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::{self, BufReader};
+use std::path::PathBuf;
 
 #[derive(Clone, Debug, Default)]
 pub struct PartData {
@@ -23,9 +24,13 @@ pub struct PartData {
 
 pub type FrameParts = Vec<PartData>;
 
+pub fn file_path(unit_id: u32, input_path: &str) -> PathBuf {
+    PathBuf::from(format!("{input_path}/unit_cgg_{unit_id}.csv"))
+}
+
 pub fn read_file(unit_id: u32, input_path: &str) -> io::Result<BufReader<File>> {
-    let file_path = format!("{input_path}/unit_cgg_{unit_id}.csv");
-    println!("[cgg] processing `cgg` file [{file_path}]");
+    let file_path = file_path(unit_id, input_path);
+    log::debug!("reading cgg file {}", file_path.display());
 
     let file = File::open(file_path)?;
     let reader = BufReader::new(file);
@@ -35,6 +40,7 @@ pub fn read_file(unit_id: u32, input_path: &str) -> io::Result<BufReader<File>>
 
 use crate::error::Result;
 
+#[allow(clippy::manual_checked_ops)]
 pub fn process(text: &str, row: usize) -> Result<Option<FrameParts>> {
     let mut params = text
         .split(",")
@@ -45,20 +51,8 @@ pub fn process(text: &str, row: usize) -> Result<Option<FrameParts>> {
         return Ok(None);
     }
 
-    let anchor: i32 = params.remove(0).parse().map_err(|_| {
-        crate::FfbeError::ParseError(format!(
-            "Invalid anchor value at line {}: '{}'",
-            row + 1,
-            params[0]
-        ))
-    })?;
-    let count: usize = params.remove(0).parse().map_err(|_| {
-        crate::FfbeError::ParseError(format!(
-            "Invalid count value at line {}: '{}'",
-            row + 1,
-            params[0]
-        ))
-    })?;
+    let anchor: i32 = parse_field(params.remove(0), "anchor", row)?;
+    let count: usize = parse_field(params.remove(0), "count", row)?;
     let chunk_size = if count == 0 { 1 } else { params.len() / count };
 
     if chunk_size == 0 {
@@ -103,11 +97,10 @@ pub fn process(text: &str, row: usize) -> Result<Option<FrameParts>> {
                 parts.push(part_data);
             }
             _ => {
-                return Err(crate::FfbeError::ParseError(format!(
-                    "Invalid chunk format at line {}: expected 11 fields, got {}",
-                    row + 1,
-                    chunk.len()
-                )));
+                return Err(crate::FfbeError::InvalidLine {
+                    line: row + 1,
+                    message: format!("part {index} has {} fields, expected 11", chunk.len()),
+                });
             }
         }
     }
@@ -116,14 +109,11 @@ pub fn process(text: &str, row: usize) -> Result<Option<FrameParts>> {
     Ok(Some(parts))
 }
 
-fn parse_field<T: std::str::FromStr>(value: &str, field_name: &str, row: usize) -> Result<T> {
-    value.parse().map_err(|_| {
-        crate::FfbeError::ParseError(format!(
-            "Invalid {} value at line {}: '{}'",
-            field_name,
-            row + 1,
-            value
-        ))
+fn parse_field<T: std::str::FromStr>(value: &str, field: &'static str, row: usize) -> Result<T> {
+    value.parse().map_err(|_| crate::FfbeError::InvalidField {
+        line: row + 1,
+        field,
+        value: value.to_string(),
     })
 }
 
@@ -214,12 +204,9 @@ mod tests {
     fn test_process_invalid_anchor() {
         let line = "invalid,1,10,20,0,1,70,0,704,1232,80,64,0";
         let result = process(line, 0);
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Invalid anchor value")
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid anchor value at line 1: 'invalid'"
         );
     }
 
@@ -252,13 +239,10 @@ mod tests {
     #[test]
     fn test_process_insufficient_chunk_fields() {
         let line = "0,1,10,20,0,1,70,0,704,1232"; // Missing fields
-        let result = process(line, 0);
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Invalid chunk format")
+        let result = process(line, 4);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid line 5: part 0 has 8 fields, expected 11"
         );
     }
 
@@ -1,14 +1,19 @@
 use image::{
-    DynamicImage, ImageBuffer, Rgba, RgbaImage,
+    DynamicImage, Rgba, RgbaImage,
     imageops::{self, overlay},
 };
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::cgg;
 use crate::constants::{CANVAS_SIZE, HALF_CANVAS};
 use crate::imageops::{BlendExt, ColorBoundsExt, OpacityExt, Rect};
+use crate::progress::{self, Progress, ProgressHook};
 
 #[derive(Clone)]
 pub struct Frame {
@@ -22,18 +27,36 @@ pub struct Frame {
 #[derive(Clone)]
 pub struct CompositeFrame {
     pub frame_idx: usize,
-    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// Shared between every `CompositeFrame` rendered from the same cgg frame and offset.
+    /// Holds only the pixels inside `rect` until the frames are placed in their final bounds.
+    pub image: Arc<RgbaImage>,
+    /// Bounds of the frame's content on the canvas.
     pub rect: Rect,
     pub delay: u32,
 }
 
+impl CompositeFrame {
+    /// Whether this is the transparent 1x1 placeholder of a frame without any content.
+    pub fn is_empty(&self) -> bool {
+        self.image.dimensions() == (1, 1) && self.image.get_pixel(0, 0).0 == [0, 0, 0, 0]
+    }
+}
+
 impl Frame {
-    pub fn composite(self, image: ImageBuffer<Rgba<u8>, Vec<u8>>, rect: Rect) -> CompositeFrame {
+    pub fn composite(self, image: Arc<RgbaImage>, rect: Rect) -> CompositeFrame {
         let Frame {
-            frame_idx, delay, ..
+            frame_idx,
+            offset_x,
+            offset_y,
+            delay,
+            ..
         } = self;
         CompositeFrame {
             frame_idx,
+            offset_x,
+            offset_y,
             image,
             rect,
             delay,
@@ -41,13 +64,104 @@ impl Frame {
     }
 }
 
+/// Identifies a processed part image: the atlas rectangle plus every
+/// transform `process_part` applies to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PartKey {
+    pub page_id: u32,
+    pub atlas_x: u32,
+    pub atlas_y: u32,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub next_type: i32,
+    pub rotate: i32,
+    pub blend_mode: i32,
+    pub opacity: i32,
+}
+
+impl From<&cgg::PartData> for PartKey {
+    fn from(part: &cgg::PartData) -> Self {
+        Self {
+            page_id: part.page_id,
+            atlas_x: part.atlas_x,
+            atlas_y: part.atlas_y,
+            atlas_width: part.atlas_width,
+            atlas_height: part.atlas_height,
+            next_type: part.next_type,
+            rotate: part.rotate,
+            blend_mode: part.blend_mode,
+            opacity: part.opacity,
+        }
+    }
+}
+
+/// Cache of processed part images shared across frames and animations.
+///
+/// Many cgg frames reuse the same atlas rectangle with identical transforms,
+/// so each distinct part is cropped and processed only once.
+#[derive(Default)]
+pub struct PartCache {
+    parts: Mutex<HashMap<PartKey, Arc<RgbaImage>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl PartCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the processed image for `part`, processing and caching it on a miss.
+    pub fn get_or_process(&self, src_img: &DynamicImage, part: &cgg::PartData) -> Arc<RgbaImage> {
+        let key = PartKey::from(part);
+
+        if let Some(img) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Arc::clone(img);
+        }
+
+        // Process outside the lock so other workers aren't blocked. If two workers
+        // race on the same key the first insert wins and both get the same image.
+        let img = Arc::new(process_part(src_img, part));
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Arc::clone(self.lock().entry(key).or_insert(img))
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of distinct processed parts held by the cache.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PartKey, Arc<RgbaImage>>> {
+        // A poisoned lock only means another worker panicked mid-insert; the map itself
+        // is still consistent.
+        self.parts.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
 /// frame_index, frame_offset_x, frame_offset_y, delay
 #[derive(Debug)]
 pub struct CgsMeta(pub usize, pub i32, pub i32, pub u32);
 
+pub fn file_path(unit_id: u32, anim_name: &str, input_path: &str) -> PathBuf {
+    PathBuf::from(format!("{input_path}/unit_{anim_name}_cgs_{unit_id}.csv"))
+}
+
 pub fn read_file(unit_id: u32, anim_name: &str, input_path: &str) -> io::Result<BufReader<File>> {
-    let file_path = format!("{input_path}/unit_{anim_name}_cgs_{unit_id}.csv");
-    println!("[cgs] processing `cgs` file [{file_path}]");
+    let file_path = file_path(unit_id, anim_name, input_path);
+    log::debug!("reading cgs file {}", file_path.display());
 
     let file = File::open(file_path)?;
     let reader = BufReader::new(file);
@@ -55,126 +169,229 @@ pub fn read_file(unit_id: u32, anim_name: &str, input_path: &str) -> io::Result<
     Ok(reader)
 }
 
-pub fn process(text: &str) -> Option<Result<CgsMeta, crate::FfbeError>> {
+/// Parses line `row` (starting at 0) of a cgs file. Returns `None` for lines without the
+/// four fields, such as empty lines.
+pub fn process(text: &str, row: usize) -> Option<Result<CgsMeta, crate::FfbeError>> {
     let params = text
         .split(",")
         .take_while(|s| !s.is_empty())
         .collect::<Vec<&str>>();
 
-    if params.len() < 4 {
-        return None;
-    }
-
     match params[..] {
         [frame_index, frame_offset_x, frame_offset_y, delay] => {
-            let frame_idx = match frame_index.parse() {
-                Ok(val) => val,
-                Err(_) => {
-                    return Some(Err(crate::FfbeError::ParseError(format!(
-                        "Invalid frame_index value: '{}'",
-                        frame_index
-                    ))));
-                }
+            let parse = || {
+                Ok(CgsMeta(
+                    parse_field(frame_index, "frame_index", row)?,
+                    parse_field(frame_offset_x, "x", row)?,
+                    parse_field(frame_offset_y, "y", row)?,
+                    parse_field(delay, "delay", row)?,
+                ))
             };
-
-            let frame_offset_x = match frame_offset_x.parse() {
-                Ok(val) => val,
-                Err(_) => {
-                    return Some(Err(crate::FfbeError::ParseError(format!(
-                        "Invalid x value: '{}'",
-                        frame_offset_x
-                    ))));
-                }
-            };
-
-            let frame_offset_y = match frame_offset_y.parse() {
-                Ok(val) => val,
-                Err(_) => {
-                    return Some(Err(crate::FfbeError::ParseError(format!(
-                        "Invalid y value: '{}'",
-                        frame_offset_y
-                    ))));
-                }
-            };
-
-            let delay = match delay.parse() {
-                Ok(val) => val,
-                Err(_) => {
-                    return Some(Err(crate::FfbeError::ParseError(format!(
-                        "Invalid delay value: '{}'",
-                        delay
-                    ))));
-                }
-            };
-
-            Some(Ok(CgsMeta(
-                frame_idx,
-                frame_offset_x,
-                frame_offset_y,
-                delay,
-            )))
+            Some(parse())
         }
         _ => None,
     }
 }
 
+fn parse_field<T: std::str::FromStr>(
+    value: &str,
+    field: &'static str,
+    row: usize,
+) -> Result<T, crate::FfbeError> {
+    value.parse().map_err(|_| crate::FfbeError::InvalidField {
+        line: row + 1,
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// Checks that line `row` (starting at 0) of a cgs file references one of the
+/// `frame_count` frames of the unit's cgg file.
+pub fn check_frame_index(
+    frame_idx: usize,
+    row: usize,
+    frame_count: usize,
+) -> Result<(), crate::FfbeError> {
+    if frame_idx < frame_count {
+        return Ok(());
+    }
+    Err(crate::FfbeError::InvalidLine {
+        line: row + 1,
+        message: format!(
+            "references cgg frame {frame_idx} but the cgg file only has {frame_count} frames"
+        ),
+    })
+}
+
 /// Process a collection of frames in parallel.
+///
+/// cgs files often repeat the same cgg frame at the same offset on consecutive rows,
+/// so each distinct `(frame_idx, offset_x, offset_y)` is composited only once and the
+/// resulting image is shared between all `CompositeFrame`s that reference it. Each
+/// composite is cropped to its content as soon as its bounds are known.
+///
+/// Processed parts are looked up in (and added to) `cache`, which may be shared
+/// between calls for different animations of the same unit.
 pub fn process_frames(
     frames: &[Frame],
     src_img: &DynamicImage,
     unit: &mut crate::Unit,
     include_empty: bool,
+    cache: &PartCache,
 ) -> Vec<CompositeFrame> {
-    let results: Vec<(CompositeFrame, Option<Rect>)> = frames
-        .par_iter()
-        .enumerate()
-        .map(|(_frame_num, frame)| {
-            let mut target_img = RgbaImage::new(CANVAS_SIZE, CANVAS_SIZE);
-            let frame_offset = (frame.offset_x as i64, frame.offset_y as i64);
-
-            for part in &frame.parts {
-                process_and_overlay_part(&mut target_img, src_img, frame_offset, part);
-            }
+    process_frames_with_progress(
+        frames,
+        src_img,
+        unit,
+        include_empty,
+        cache,
+        "",
+        &progress::no_progress,
+    )
+}
 
+/// [`process_frames`], reporting [`Progress::FrameComposited`] for the animation
+/// `anim_name` to `progress` as each distinct frame is composited.
+pub fn process_frames_with_progress(
+    frames: &[Frame],
+    src_img: &DynamicImage,
+    unit: &mut crate::Unit,
+    include_empty: bool,
+    cache: &PartCache,
+    anim_name: &str,
+    progress: &dyn ProgressHook,
+) -> Vec<CompositeFrame> {
+    let (unique_frames, frame_slots) = unique_frames(frames);
+    let total = unique_frames.len();
+    let composited = AtomicUsize::new(0);
+    let composites: Vec<(Arc<RgbaImage>, Option<Rect>)> = unique_frames
+        .par_iter()
+        .map(|frame| {
+            let target_img = composite_on_canvas(frame, src_img, cache);
             let bounds_rect = target_img.get_color_bounds_rect(Rgba([0, 0, 0, 0]), false);
+            let done = composited.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.progress(Progress::FrameComposited {
+                name: anim_name,
+                done,
+                total,
+            });
 
             match bounds_rect {
-                Some(rect) => (frame.clone().composite(target_img, rect), Some(rect)),
-                None => {
-                    // Create an empty frame - we'll resize it later to match other frames
-                    let empty_rect = Rect {
-                        x: 0,
-                        y: 0,
-                        width: 1,
-                        height: 1,
-                    };
-                    let empty_img = RgbaImage::new(1, 1);
-                    (frame.clone().composite(empty_img, empty_rect), None)
+                // Keep only the content so that long animations do not hold a full canvas
+                // per frame
+                Some(rect) => {
+                    let content = imageops::crop_imm(
+                        &target_img,
+                        rect.x as u32,
+                        rect.y as u32,
+                        rect.width,
+                        rect.height,
+                    );
+                    (Arc::new(content.to_image()), Some(rect))
                 }
+                // Create an empty frame - we'll resize it later to match other frames
+                None => (Arc::new(RgbaImage::new(1, 1)), None),
             }
         })
         .collect();
 
     // Update unit bounds after parallel processing (only for non-empty frames)
-    for (_, rect_opt) in &results {
+    for (_, rect_opt) in &composites {
         if let Some(rect) = rect_opt {
             merge_bounding_box(unit, rect);
         }
     }
 
     // Filter frames based on include_empty flag
-    results
-        .into_iter()
-        .filter_map(|(cf, rect_opt)| {
-            if rect_opt.is_some() || include_empty {
-                Some(cf)
-            } else {
-                None
+    frames
+        .iter()
+        .zip(frame_slots)
+        .filter_map(|(frame, slot)| {
+            let (image, rect_opt) = &composites[slot];
+            match rect_opt {
+                Some(rect) => Some(frame.clone().composite(Arc::clone(image), *rect)),
+                None if include_empty => {
+                    let empty_rect = Rect {
+                        x: 0,
+                        y: 0,
+                        width: 1,
+                        height: 1,
+                    };
+                    Some(frame.clone().composite(Arc::clone(image), empty_rect))
+                }
+                None => None,
             }
         })
         .collect()
 }
 
+/// Merges the bounds of every frame into the unit's bounds like [`process_frames`], without
+/// keeping any pixels. Used to measure animations before rendering them.
+pub fn measure_frames(
+    frames: &[Frame],
+    src_img: &DynamicImage,
+    unit: &mut crate::Unit,
+    cache: &PartCache,
+) {
+    let (unique_frames, _) = unique_frames(frames);
+    let bounds: Vec<Rect> = unique_frames
+        .par_iter()
+        .filter_map(|frame| {
+            composite_on_canvas(frame, src_img, cache)
+                .get_color_bounds_rect(Rgba([0, 0, 0, 0]), false)
+        })
+        .collect();
+
+    for rect in &bounds {
+        merge_bounding_box(unit, rect);
+    }
+}
+
+/// Returns the first frame of every distinct `(frame_idx, offset_x, offset_y)` and, for
+/// each frame, the index of its distinct frame.
+fn unique_frames(frames: &[Frame]) -> (Vec<&Frame>, Vec<usize>) {
+    let mut unique_indices: HashMap<(usize, i32, i32), usize> = HashMap::new();
+    let mut unique_frames: Vec<&Frame> = Vec::new();
+    let frame_slots: Vec<usize> = frames
+        .iter()
+        .map(|frame| {
+            *unique_indices
+                .entry((frame.frame_idx, frame.offset_x, frame.offset_y))
+                .or_insert_with(|| {
+                    unique_frames.push(frame);
+                    unique_frames.len() - 1
+                })
+        })
+        .collect();
+    (unique_frames, frame_slots)
+}
+
+fn composite_on_canvas(frame: &Frame, src_img: &DynamicImage, cache: &PartCache) -> RgbaImage {
+    let mut target_img = RgbaImage::new(CANVAS_SIZE, CANVAS_SIZE);
+    let frame_offset = (frame.offset_x as i64, frame.offset_y as i64);
+
+    for part in &frame.parts {
+        process_and_overlay_part(&mut target_img, src_img, frame_offset, part, cache);
+    }
+    target_img
+}
+
+/// Collapses runs of consecutive frames that share the same image into a single
+/// frame whose delay is the sum of the run. Intended for animated outputs, where a
+/// repeated frame is indistinguishable from one frame held for longer.
+pub fn collapse_repeated_frames(frames: &[CompositeFrame]) -> Vec<CompositeFrame> {
+    let mut collapsed: Vec<CompositeFrame> = Vec::with_capacity(frames.len());
+
+    for frame in frames {
+        match collapsed.last_mut() {
+            Some(prev) if Arc::ptr_eq(&prev.image, &frame.image) => prev.delay += frame.delay,
+            _ => collapsed.push(frame.clone()),
+        }
+    }
+
+    collapsed
+}
+
 /// Processes a single part into a ready-to-overlay image.
 fn process_part(src_img: &DynamicImage, part: &cgg::PartData) -> RgbaImage {
     let cgg::PartData {
@@ -247,12 +464,13 @@ fn process_and_overlay_part(
     src_img: &DynamicImage,
     frame_offset: (i64, i64),
     part: &cgg::PartData,
+    cache: &PartCache,
 ) {
-    let part_img = process_part(src_img, part);
+    let part_img = cache.get_or_process(src_img, part);
 
     overlay(
         target_img,
-        &part_img,
+        part_img.as_ref(),
         HALF_CANVAS as i64 + frame_offset.0 + part.canvas_x as i64,
         HALF_CANVAS as i64 + frame_offset.1 + part.canvas_y as i64,
     );
@@ -264,20 +482,20 @@ mod tests {
 
     #[test]
     fn test_process_empty_line() {
-        let result = process("");
+        let result = process("", 0);
         assert!(result.is_none());
     }
 
     #[test]
     fn test_process_insufficient_params() {
-        let result = process("1,2,3");
+        let result = process("1,2,3", 0);
         assert!(result.is_none());
     }
 
     #[test]
     fn test_process_valid_line() {
         let line = "5,10,20,100";
-        let result = process(line).unwrap().unwrap();
+        let result = process(line, 0).unwrap().unwrap();
 
         assert_eq!(result.0, 5); // frame_index
         assert_eq!(result.1, 10); // x
@@ -288,7 +506,7 @@ mod tests {
     #[test]
     fn test_process_negative_values() {
         let line = "0,-10,-20,50";
-        let result = process(line).unwrap().unwrap();
+        let result = process(line, 0).unwrap().unwrap();
 
         assert_eq!(result.0, 0);
         assert_eq!(result.1, -10);
@@ -299,7 +517,7 @@ mod tests {
     #[test]
     fn test_process_invalid_frame_index() {
         let line = "invalid,10,20,100";
-        let result = process(line).unwrap();
+        let result = process(line, 0).unwrap();
         assert!(result.is_err());
         assert!(
             result
@@ -312,7 +530,7 @@ mod tests {
     #[test]
     fn test_process_invalid_x() {
         let line = "5,invalid,20,100";
-        let result = process(line).unwrap();
+        let result = process(line, 0).unwrap();
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Invalid x value"));
     }
@@ -320,7 +538,7 @@ mod tests {
     #[test]
     fn test_process_invalid_y() {
         let line = "5,10,invalid,100";
-        let result = process(line).unwrap();
+        let result = process(line, 0).unwrap();
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Invalid y value"));
     }
@@ -328,13 +546,20 @@ mod tests {
     #[test]
     fn test_process_invalid_delay() {
         let line = "5,10,20,invalid";
-        let result = process(line).unwrap();
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Invalid delay value")
+        let err = process(line, 6).unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid delay value at line 7: 'invalid'");
+        assert_eq!((err.line(), err.field()), (Some(7), Some("delay")));
+    }
+
+    #[test]
+    fn test_check_frame_index() {
+        assert!(check_frame_index(1, 0, 2).is_ok());
+
+        let err = check_frame_index(9999, 4, 2).unwrap_err();
+        assert_eq!(err.line(), Some(5));
+        assert_eq!(
+            err.to_string(),
+            "Invalid line 5: references cgg frame 9999 but the cgg file only has 2 frames"
         );
     }
 
@@ -357,8 +582,10 @@ mod tests {
             height: 50,
         };
 
-        let composite = frame.composite(image, rect);
+        let composite = frame.composite(Arc::new(image), rect);
         assert_eq!(composite.frame_idx, 1);
+        assert_eq!(composite.offset_x, 10);
+        assert_eq!(composite.offset_y, 20);
         assert_eq!(composite.delay, 100);
         assert_eq!(composite.rect.width, 50);
         assert_eq!(composite.rect.height, 50);
@@ -433,18 +660,146 @@ mod tests {
         let test_img = DynamicImage::new_rgba8(10, 10);
         let mut unit = crate::Unit::default();
 
+        let cache = PartCache::new();
+
         // Test with include_empty = false (should filter out empty frames)
-        let result_no_empty = process_frames(&frames, &test_img, &mut unit, false);
+        let result_no_empty = process_frames(&frames, &test_img, &mut unit, false, &cache);
         assert_eq!(result_no_empty.len(), 0);
 
         // Reset unit for second test
         let mut unit2 = crate::Unit::default();
 
         // Test with include_empty = true (should include empty frames)
-        let result_with_empty = process_frames(&frames, &test_img, &mut unit2, true);
+        let result_with_empty = process_frames(&frames, &test_img, &mut unit2, true, &cache);
         assert_eq!(result_with_empty.len(), 1);
         // Empty frames start as 1x1 - they get resized later in main.rs
         assert_eq!(result_with_empty[0].image.width(), 1);
         assert_eq!(result_with_empty[0].image.height(), 1);
     }
+
+    #[test]
+    fn test_process_frames_shares_repeated_frames() {
+        let mut src_img = RgbaImage::new(8, 8);
+        src_img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let src_img = DynamicImage::from(src_img);
+
+        let parts = vec![cgg::PartData {
+            atlas_width: 2,
+            atlas_height: 2,
+            opacity: 100,
+            ..Default::default()
+        }];
+        let frame = |offset_x, delay| Frame {
+            frame_idx: 0,
+            parts: parts.clone(),
+            offset_x,
+            offset_y: 0,
+            delay,
+        };
+        let frames = vec![frame(0, 2), frame(0, 3), frame(5, 4), frame(0, 1)];
+
+        let mut unit = crate::Unit::default();
+        let cache = PartCache::new();
+        let events = Mutex::new(Vec::new());
+        let record = |progress: Progress| {
+            if let Progress::FrameComposited { done, total, .. } = progress {
+                events.lock().unwrap().push((done, total));
+            }
+        };
+        let result = process_frames_with_progress(
+            &frames, &src_img, &mut unit, false, &cache, "atk", &record,
+        );
+
+        let mut events = events.into_inner().unwrap();
+        events.sort();
+        assert_eq!(events, vec![(1, 2), (2, 2)]);
+        assert_eq!(result.len(), 4);
+        assert!(Arc::ptr_eq(&result[0].image, &result[1].image));
+        assert!(Arc::ptr_eq(&result[0].image, &result[3].image));
+        assert!(!Arc::ptr_eq(&result[0].image, &result[2].image));
+        assert_eq!(result[2].rect.x, result[0].rect.x + 5);
+        // Only one distinct part was processed despite two distinct composites
+        assert_eq!(cache.misses(), 1);
+        // Composites keep only their content
+        assert_eq!(result[0].image.dimensions(), (1, 1));
+        assert_eq!((result[0].rect.width, result[0].rect.height), (1, 1));
+        assert!(!result[0].is_empty());
+
+        let mut measured_unit = crate::Unit::default();
+        measure_frames(&frames, &src_img, &mut measured_unit, &cache);
+        let corners = |unit: &crate::Unit| {
+            [unit.top_left, unit.bottom_right].map(|point| point.map(|p| (p.x(), p.y())))
+        };
+        assert_eq!(corners(&measured_unit), corners(&unit));
+    }
+
+    #[test]
+    fn test_collapse_repeated_frames() {
+        let shared = Arc::new(RgbaImage::new(1, 1));
+        let other = Arc::new(RgbaImage::new(1, 1));
+        let rect = Rect::default();
+        let frame = |image: &Arc<RgbaImage>, delay| CompositeFrame {
+            frame_idx: 0,
+            offset_x: 0,
+            offset_y: 0,
+            image: Arc::clone(image),
+            rect,
+            delay,
+        };
+        let frames = vec![
+            frame(&shared, 2),
+            frame(&shared, 3),
+            frame(&other, 4),
+            frame(&shared, 1),
+        ];
+
+        let collapsed = collapse_repeated_frames(&frames);
+
+        let delays: Vec<u32> = collapsed.iter().map(|f| f.delay).collect();
+        assert_eq!(delays, vec![5, 4, 1]);
+    }
+
+    #[test]
+    fn test_part_cache_reuses_processed_parts() {
+        let src_img = DynamicImage::new_rgba8(16, 16);
+        let part = cgg::PartData {
+            atlas_width: 4,
+            atlas_height: 4,
+            opacity: 100,
+            ..Default::default()
+        };
+        let cache = PartCache::new();
+
+        let first = cache.get_or_process(&src_img, &part);
+        let second = cache.get_or_process(&src_img, &part);
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_part_cache_distinguishes_transforms() {
+        let src_img = DynamicImage::new_rgba8(16, 16);
+        let part = cgg::PartData {
+            atlas_width: 4,
+            atlas_height: 8,
+            opacity: 100,
+            ..Default::default()
+        };
+        let rotated = cgg::PartData {
+            rotate: 90,
+            ..part.clone()
+        };
+        let cache = PartCache::new();
+
+        let plain_img = cache.get_or_process(&src_img, &part);
+        let rotated_img = cache.get_or_process(&src_img, &rotated);
+
+        assert_eq!(plain_img.dimensions(), (4, 8));
+        assert_eq!(rotated_img.dimensions(), (8, 4));
+        assert_eq!(cache.misses(), 2);
+        assert_eq!(cache.len(), 2);
+    }
 }
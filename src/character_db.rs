@@ -24,6 +24,7 @@ impl Db {
     pub fn new() -> Self {
         Self(HashMap::<u32, CharacterInfo>::new())
     }
+    #[allow(clippy::question_mark)]
     pub fn from_file(path: &str) -> Result<Self> {
         let contents = match fs::read_to_string(path) {
             Ok(contents) => contents,
@@ -39,6 +40,9 @@ impl Db {
     pub fn insert(&mut self, key: u32, val: CharacterInfo) {
         self.0.insert(key, val);
     }
+    pub fn get(&self, uid: u32) -> Option<&CharacterInfo> {
+        self.0.get(&uid)
+    }
     pub fn iter(&self) -> std::collections::hash_map::Iter<'_, u32, CharacterInfo> {
         self.0.iter()
     }
@@ -0,0 +1,226 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{FfbeError, Result};
+
+/// File name of both the project-local and the user-level configuration.
+pub const CONFIG_FILE: &str = "ffbetool.toml";
+
+/// Default options read from `ffbetool.toml`.
+///
+/// Every table maps long option names (without the leading `--`) to values, e.g.
+/// `columns = 8`, `json = true` or `anim = ["limit_*"]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Options applied to every run.
+    #[serde(default)]
+    pub defaults: toml::Table,
+    /// Named option sets selected with `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, toml::Table>,
+    /// Options for a single unit, keyed by the unit id or name as given on the command line.
+    #[serde(default)]
+    pub units: BTreeMap<String, toml::Table>,
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|err| FfbeError::from(err).in_file(path))?;
+        toml::from_str(&contents).map_err(|err| FfbeError::from(err).in_file(path))
+    }
+
+    /// Loads `explicit` if given. Otherwise merges the user-level configuration with
+    /// `ffbetool.toml` in the working directory, the project-local file taking precedence.
+    /// Missing files are skipped.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        if let Some(path) = explicit {
+            return Self::from_file(path);
+        }
+
+        let mut config = Self::default();
+        for path in [user_config_path(), Some(PathBuf::from(CONFIG_FILE))]
+            .into_iter()
+            .flatten()
+        {
+            if path.is_file() {
+                config.merge(Self::from_file(&path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Merges `other` into this configuration, with values from `other` winning.
+    pub fn merge(&mut self, other: Config) {
+        self.defaults.extend(other.defaults);
+        for (name, options) in other.profiles {
+            self.profiles.entry(name).or_default().extend(options);
+        }
+        for (unit, options) in other.units {
+            self.units.entry(unit).or_default().extend(options);
+        }
+    }
+
+    /// Returns the options for a run: the defaults, overridden by the selected profile,
+    /// overridden by the unit's own options.
+    pub fn resolve(&self, profile: Option<&str>, unit: &str) -> Result<toml::Table> {
+        let mut options = self.defaults.clone();
+
+        if let Some(name) = profile {
+            let profile = self.profiles.get(name).ok_or_else(|| {
+                FfbeError::InvalidInput(format!("Profile '{name}' is not defined in {CONFIG_FILE}"))
+            })?;
+            options.extend(profile.clone());
+        }
+        if let Some(unit_options) = self.units.get(unit) {
+            options.extend(unit_options.clone());
+        }
+
+        Ok(options)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ffbetool/ffbetool.toml`, falling back to `~/.config/ffbetool/ffbetool.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("ffbetool").join(CONFIG_FILE))
+}
+
+/// Converts a configured option into command line arguments: `--key value` for scalars,
+/// one `--key value` per element for arrays, and `--key` for `true` (nothing for `false`).
+/// A `counted` flag such as `verbose` is repeated as often as an integer value says.
+pub fn to_cli_args(key: &str, value: &toml::Value, counted: bool) -> Result<Vec<String>> {
+    let flag = format!("--{key}");
+    let scalar = |value: &toml::Value| match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => Err(FfbeError::InvalidInput(format!(
+            "Unsupported value for '{key}' in {CONFIG_FILE}: {value}"
+        ))),
+    };
+
+    match value {
+        toml::Value::Integer(count) if counted => {
+            let count = usize::try_from(*count).map_err(|_| {
+                FfbeError::InvalidInput(format!(
+                    "'{key}' in {CONFIG_FILE} must not be negative: {count}"
+                ))
+            })?;
+            Ok(vec![flag; count])
+        }
+        toml::Value::Boolean(true) => Ok(vec![flag]),
+        toml::Value::Boolean(false) => Ok(vec![]),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|value| Ok([flag.clone(), scalar(value)?]))
+            .collect::<Result<Vec<_>>>()
+            .map(|pairs| pairs.concat()),
+        value => Ok(vec![flag, scalar(value)?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [defaults]
+        input = "assets"
+        json = true
+        columns = 4
+
+        [profiles.web]
+        apng = true
+        columns = 8
+
+        [units.401012417]
+        columns = 10
+        anim = ["limit_*", "idle"]
+    "#;
+
+    #[test]
+    fn test_resolve_layers_options() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+
+        let options = config.resolve(None, "100000102").unwrap();
+        assert_eq!(options["columns"].as_integer(), Some(4));
+        assert_eq!(options["input"].as_str(), Some("assets"));
+
+        let options = config.resolve(Some("web"), "100000102").unwrap();
+        assert_eq!(options["columns"].as_integer(), Some(8));
+        assert_eq!(options["apng"].as_bool(), Some(true));
+
+        let options = config.resolve(Some("web"), "401012417").unwrap();
+        assert_eq!(options["columns"].as_integer(), Some(10));
+        assert_eq!(options["json"].as_bool(), Some(true));
+
+        assert!(config.resolve(Some("godot"), "401012417").is_err());
+    }
+
+    #[test]
+    fn test_merge_prefers_other() {
+        let mut user: Config = toml::from_str(CONFIG).unwrap();
+        let project: Config =
+            toml::from_str("[defaults]\ncolumns = 2\n[profiles.web]\nscale = 2\n").unwrap();
+
+        user.merge(project);
+
+        assert_eq!(user.defaults["columns"].as_integer(), Some(2));
+        assert_eq!(user.defaults["input"].as_str(), Some("assets"));
+        assert_eq!(user.profiles["web"]["scale"].as_integer(), Some(2));
+        assert_eq!(user.profiles["web"]["apng"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_to_cli_args() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let unit = &config.units["401012417"];
+
+        assert_eq!(
+            to_cli_args("columns", &unit["columns"], false).unwrap(),
+            vec!["--columns", "10"]
+        );
+        assert_eq!(
+            to_cli_args("anim", &unit["anim"], false).unwrap(),
+            vec!["--anim", "limit_*", "--anim", "idle"]
+        );
+        assert_eq!(
+            to_cli_args("json", &toml::Value::Boolean(true), false).unwrap(),
+            vec!["--json"]
+        );
+        assert!(
+            to_cli_args("json", &toml::Value::Boolean(false), false)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(to_cli_args("layout", &toml::Value::Table(toml::Table::new()), false).is_err());
+
+        assert_eq!(
+            to_cli_args("verbose", &toml::Value::Integer(2), true).unwrap(),
+            vec!["--verbose", "--verbose"]
+        );
+        assert_eq!(
+            to_cli_args("verbose", &toml::Value::Boolean(true), true).unwrap(),
+            vec!["--verbose"]
+        );
+        assert!(to_cli_args("verbose", &toml::Value::Integer(-1), true).is_err());
+    }
+
+    #[test]
+    fn test_from_file_rejects_invalid_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE);
+
+        std::fs::write(&path, "[default]\ncolumns = 4\n").unwrap();
+        assert!(Config::from_file(&path).is_err());
+
+        std::fs::write(&path, CONFIG).unwrap();
+        assert!(Config::from_file(&path).is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+use image::{Rgba, RgbaImage};
+
+use crate::cgg;
+use crate::constants::HALF_CANVAS;
+use crate::imageops::Rect;
+
+/// Outline colour for parts drawn with the normal blend mode.
+pub const NORMAL_PART_COLOR: Rgba<u8> = Rgba([0, 255, 0, 255]);
+/// Outline colour for parts drawn with the additive (`blend_mode == 1`) blend mode.
+pub const ADDITIVE_PART_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+/// Outline colour for parts with any other blend mode value.
+pub const UNKNOWN_PART_COLOR: Rgba<u8> = Rgba([255, 255, 0, 255]);
+pub const ORIGIN_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+pub const FRAME_RECT_COLOR: Rgba<u8> = Rgba([0, 255, 255, 255]);
+
+const ORIGIN_ARM_LENGTH: i32 = 6;
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+pub fn part_color(blend_mode: i32) -> Rgba<u8> {
+    match blend_mode {
+        0 => NORMAL_PART_COLOR,
+        1 => ADDITIVE_PART_COLOR,
+        _ => UNKNOWN_PART_COLOR,
+    }
+}
+
+/// Returns where `part` lands on the canvas for a frame with the given cgs offset.
+/// Parts rotated by a quarter turn swap their atlas width and height.
+pub fn part_rect(part: &cgg::PartData, offset_x: i32, offset_y: i32) -> Rect {
+    let (width, height) = match part.rotate {
+        90 | -90 | 270 | -270 => (part.atlas_height, part.atlas_width),
+        _ => (part.atlas_width, part.atlas_height),
+    };
+
+    Rect {
+        x: HALF_CANVAS as i32 + offset_x + part.canvas_x,
+        y: HALF_CANVAS as i32 + offset_y + part.canvas_y,
+        width,
+        height,
+    }
+}
+
+/// Draws the debug overlay onto a frame already cropped to `frame_rect`: each part's
+/// destination rect labelled `index:line` (the part's index within its cgg line and the
+/// 1-based cgg line number), the origin cross at the cgs offset and the frame rect outline.
+pub fn draw_frame_overlay(
+    image: &RgbaImage,
+    parts: &[cgg::PartData],
+    offset_x: i32,
+    offset_y: i32,
+    frame_rect: Rect,
+) -> RgbaImage {
+    let mut image = image.clone();
+    let to_local = |x: i32, y: i32| (x - frame_rect.x, y - frame_rect.y);
+
+    for part in parts {
+        let rect = part_rect(part, offset_x, offset_y);
+        let color = part_color(part.blend_mode);
+        let (x, y) = to_local(rect.x, rect.y);
+        draw_rect_outline(&mut image, x, y, rect.width, rect.height, color);
+
+        let label = format!("{}:{}", part.index, part.line_index + 1);
+        draw_text(&mut image, x + 2, y + 2, &label, color);
+    }
+
+    let (origin_x, origin_y) =
+        to_local(HALF_CANVAS as i32 + offset_x, HALF_CANVAS as i32 + offset_y);
+    draw_cross(&mut image, origin_x, origin_y, ORIGIN_COLOR);
+    draw_rect_outline(
+        &mut image,
+        0,
+        0,
+        frame_rect.width,
+        frame_rect.height,
+        FRAME_RECT_COLOR,
+    );
+
+    image
+}
+
+fn put_pixel(image: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+pub(crate) fn draw_rect_outline(
+    image: &mut RgbaImage,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let right = x + width as i32 - 1;
+    let bottom = y + height as i32 - 1;
+    for px in x..=right {
+        put_pixel(image, px, y, color);
+        put_pixel(image, px, bottom, color);
+    }
+    for py in y..=bottom {
+        put_pixel(image, x, py, color);
+        put_pixel(image, right, py, color);
+    }
+}
+
+fn draw_cross(image: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>) {
+    for d in -ORIGIN_ARM_LENGTH..=ORIGIN_ARM_LENGTH {
+        put_pixel(image, x + d, y, color);
+        put_pixel(image, x, y + d, color);
+    }
+}
+
+/// Rows of a 3x5 bitmap glyph, most significant of the low three bits on the left.
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+fn draw_text(image: &mut RgbaImage, x: i32, y: i32, text: &str, color: Rgba<u8>) {
+    let mut cursor = x;
+    for c in text.chars() {
+        if let Some(rows) = glyph(c) {
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        put_pixel(image, cursor + col, y + row as i32, color);
+                    }
+                }
+            }
+        }
+        cursor += GLYPH_WIDTH + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(rotate: i32, blend_mode: i32) -> cgg::PartData {
+        cgg::PartData {
+            canvas_x: -10,
+            canvas_y: 5,
+            blend_mode,
+            rotate,
+            atlas_width: 20,
+            atlas_height: 8,
+            index: 1,
+            line_index: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_part_rect() {
+        let rect = part_rect(&part(0, 0), 3, -4);
+        assert_eq!((rect.x, rect.y), (993, 1001));
+        assert_eq!((rect.width, rect.height), (20, 8));
+
+        let rotated = part_rect(&part(-90, 0), 0, 0);
+        assert_eq!((rotated.width, rotated.height), (8, 20));
+    }
+
+    #[test]
+    fn test_part_color_by_blend_mode() {
+        assert_eq!(part_color(0), NORMAL_PART_COLOR);
+        assert_eq!(part_color(1), ADDITIVE_PART_COLOR);
+        assert_eq!(part_color(7), UNKNOWN_PART_COLOR);
+    }
+
+    #[test]
+    fn test_draw_frame_overlay() {
+        let frame_rect = Rect {
+            x: 980,
+            y: 990,
+            width: 40,
+            height: 30,
+        };
+        let image = RgbaImage::new(frame_rect.width, frame_rect.height);
+
+        let overlay = draw_frame_overlay(&image, &[part(0, 1)], 0, 0, frame_rect);
+
+        // Frame rect outline along the image border
+        assert_eq!(*overlay.get_pixel(0, 0), FRAME_RECT_COLOR);
+        assert_eq!(*overlay.get_pixel(39, 29), FRAME_RECT_COLOR);
+        // Origin cross at the canvas centre
+        assert_eq!(*overlay.get_pixel(20, 10), ORIGIN_COLOR);
+        assert_eq!(
+            *overlay.get_pixel(20 + ORIGIN_ARM_LENGTH as u32, 10),
+            ORIGIN_COLOR
+        );
+        // Part outline starts at canvas (990, 1005) → local (10, 15)
+        assert_eq!(*overlay.get_pixel(10, 15), ADDITIVE_PART_COLOR);
+        assert_eq!(*overlay.get_pixel(29, 22), ADDITIVE_PART_COLOR);
+        // Label "1:3" top row: the `1` glyph's top row is 0b010
+        assert_eq!(*overlay.get_pixel(13, 17), ADDITIVE_PART_COLOR);
+        assert_eq!(overlay.get_pixel(12, 17).0[3], 0);
+        // The source image is left untouched
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+    }
+}
@@ -51,6 +51,67 @@ pub fn discover_animations(unit_id: u32, input_dir: &str) -> Result<Vec<Discover
     Ok(animations)
 }
 
+/// Returns true if `pattern` contains glob wildcards (`*` or `?`).
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Matches `name` against a glob `pattern` where `*` matches any run of characters
+/// (including none) and `?` matches exactly one. Patterns without wildcards must match exactly.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Greedy matching with backtracking to the most recent `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Keeps the animations matching any `include` pattern (all of them when `include` is
+/// empty) and none of the `exclude` patterns, preserving order.
+pub fn filter_animations(
+    animations: Vec<DiscoveredAnimation>,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<DiscoveredAnimation> {
+    animations
+        .into_iter()
+        .filter(|animation| {
+            include.is_empty()
+                || include
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, &animation.name))
+        })
+        .filter(|animation| {
+            !exclude
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &animation.name))
+        })
+        .collect()
+}
+
 /// Extracts animation name from CGS filename
 /// Expected format: unit_{animation_name}_cgs_{unit_id}.csv
 fn extract_animation_name(filename: &str, unit_id: u32) -> Option<String> {
@@ -168,4 +229,57 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Input directory"));
     }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("atk", "atk"));
+        assert!(!matches_pattern("atk", "limit_atk"));
+        assert!(matches_pattern("limit_*", "limit_atk"));
+        assert!(!matches_pattern("limit_*", "atk"));
+        assert!(matches_pattern("*atk*", "limit_atk_2"));
+        assert!(matches_pattern("*atk", "magic_atk"));
+        assert!(!matches_pattern("*atk", "atk_end"));
+        assert!(matches_pattern("a?k", "atk"));
+        assert!(!matches_pattern("a?k", "ak"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*_*_*", "a_b_c"));
+        assert!(!matches_pattern("*_*_*", "a_b"));
+    }
+
+    #[test]
+    fn test_filter_animations() {
+        let animations: Vec<DiscoveredAnimation> = ["atk", "idle", "limit_atk", "limit_standby"]
+            .iter()
+            .map(|name| DiscoveredAnimation {
+                name: name.to_string(),
+                file_path: format!("unit_{name}_cgs_1.csv"),
+            })
+            .collect();
+        let names = |filtered: Vec<DiscoveredAnimation>| {
+            filtered.into_iter().map(|a| a.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(filter_animations(animations.clone(), &[], &[])).len(),
+            4
+        );
+        assert_eq!(
+            names(filter_animations(
+                animations.clone(),
+                &["limit_*".to_string(), "idle".to_string()],
+                &[]
+            )),
+            vec!["idle", "limit_atk", "limit_standby"]
+        );
+        assert_eq!(
+            names(filter_animations(
+                animations,
+                &["*atk*".to_string()],
+                &["limit_*".to_string()]
+            )),
+            vec!["atk"]
+        );
+        assert!(is_glob("limit_*"));
+        assert!(!is_glob("limit_atk"));
+    }
 }
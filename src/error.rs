@@ -1,4 +1,5 @@
 use std::fmt;
+use std::path::{Path, PathBuf};
 
 use apng::errors::APNGError;
 
@@ -12,32 +13,180 @@ pub enum FfbeError {
     ImageError(image::ImageError),
     ApngError(APNGError),
     ParseError(String),
+    /// A field of a cgg or cgs line that is not a valid value. `line` starts at 1.
+    InvalidField {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+    /// A cgg or cgs line that does not have the expected fields. `line` starts at 1.
+    InvalidLine {
+        line: usize,
+        message: String,
+    },
     UreqError(String),
     FileNotFound(String),
     InvalidInput(String),
     NotImplemented(String),
     MissingValue(String),
+    /// An error reading or parsing the file at `path`.
+    File {
+        path: PathBuf,
+        source: Box<FfbeError>,
+    },
+    /// An error rendering one animation of a unit.
+    Animation {
+        unit_id: u32,
+        animation: String,
+        source: Box<FfbeError>,
+    },
 }
 
+/// Shows the error itself; the alternate form (`{:#}`) follows it with its causes, e.g.
+/// `Failed to read unit_atk_cgs_1.csv: Invalid delay value at line 3: 'x'`.
 impl fmt::Display for FfbeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            FfbeError::CharacterNotFound(name) => write!(f, "Character not found in DB: {}", name),
-            FfbeError::NoDatabaseFile => write!(f, "No database file found"),
-            FfbeError::IoError(err) => write!(f, "IO errorerr: {err}"),
-            FfbeError::ImageError(err) => write!(f, "Image errorerr: {err}"),
-            FfbeError::ApngError(err) => write!(f, "APNG errorerr: {err}"),
-            FfbeError::UreqError(err) => write!(f, "Fetch errorerr: {err}"),
-            FfbeError::ParseError(msg) => write!(f, "Parse error: {msg}"),
-            FfbeError::FileNotFound(path) => write!(f, "File not found: {path}"),
-            FfbeError::InvalidInput(msg) => write!(f, "Invalid input: {msg}"),
-            FfbeError::NotImplemented(msg) => write!(f, "Not implemented: {msg}"),
-            FfbeError::MissingValue(msg) => write!(f, "Missing value: {msg}"),
+            FfbeError::CharacterNotFound(name) => write!(f, "Character not found in DB: {}", name)?,
+            FfbeError::NoDatabaseFile => write!(f, "No database file found")?,
+            FfbeError::IoError(err) => write!(f, "IO error: {err}")?,
+            FfbeError::ImageError(err) => write!(f, "Image error: {err}")?,
+            FfbeError::ApngError(err) => write!(f, "APNG error: {err}")?,
+            FfbeError::UreqError(err) => write!(f, "Fetch error: {err}")?,
+            FfbeError::ParseError(msg) => write!(f, "Parse error: {msg}")?,
+            FfbeError::InvalidField { line, field, value } => {
+                write!(f, "Invalid {field} value at line {line}: '{value}'")?
+            }
+            FfbeError::InvalidLine { line, message } => {
+                write!(f, "Invalid line {line}: {message}")?
+            }
+            FfbeError::FileNotFound(path) => write!(f, "File not found: {path}")?,
+            FfbeError::InvalidInput(msg) => write!(f, "Invalid input: {msg}")?,
+            FfbeError::NotImplemented(msg) => write!(f, "Not implemented: {msg}")?,
+            FfbeError::MissingValue(msg) => write!(f, "Missing value: {msg}")?,
+            FfbeError::File { path, .. } => write!(f, "Failed to read {}", path.display())?,
+            FfbeError::Animation {
+                unit_id, animation, ..
+            } => write!(
+                f,
+                "Failed to render animation {animation} of unit {unit_id}"
+            )?,
+        }
+
+        if f.alternate() {
+            let mut source = std::error::Error::source(self);
+            while let Some(cause) = source {
+                write!(f, ": {cause}")?;
+                source = cause.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The io, image and APNG errors are shown as part of the error itself, so their causes
+/// come next.
+impl std::error::Error for FfbeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FfbeError::IoError(err) => std::error::Error::source(err),
+            FfbeError::ImageError(err) => std::error::Error::source(err),
+            FfbeError::ApngError(err) => std::error::Error::source(err),
+            FfbeError::File { source, .. } | FfbeError::Animation { source, .. } => {
+                Some(source.as_ref())
+            }
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for FfbeError {}
+impl FfbeError {
+    /// Short, stable name of the error variant for machine-readable reports. Context added
+    /// by [`FfbeError::in_file`] and [`FfbeError::in_animation`] is skipped.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FfbeError::CharacterNotFound(_) => "character_not_found",
+            FfbeError::NoDatabaseFile => "no_database_file",
+            FfbeError::IoError(_) => "io",
+            FfbeError::ImageError(_) => "image",
+            FfbeError::ApngError(_) => "apng",
+            FfbeError::ParseError(_)
+            | FfbeError::InvalidField { .. }
+            | FfbeError::InvalidLine { .. } => "parse",
+            FfbeError::UreqError(_) => "fetch",
+            FfbeError::FileNotFound(_) => "file_not_found",
+            FfbeError::InvalidInput(_) => "invalid_input",
+            FfbeError::NotImplemented(_) => "not_implemented",
+            FfbeError::MissingValue(_) => "missing_value",
+            FfbeError::File { source, .. } | FfbeError::Animation { source, .. } => source.kind(),
+        }
+    }
+
+    /// Records that the error happened while reading or parsing `path`.
+    pub fn in_file(self, path: impl Into<PathBuf>) -> Self {
+        FfbeError::File {
+            path: path.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Records that the error happened while rendering `animation` of `unit_id`.
+    pub fn in_animation(self, unit_id: u32, animation: &str) -> Self {
+        FfbeError::Animation {
+            unit_id,
+            animation: animation.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// This error followed by the `FfbeError`s it wraps.
+    fn chain(&self) -> impl Iterator<Item = &FfbeError> {
+        std::iter::successors(Some(self), |err| match err {
+            FfbeError::File { source, .. } | FfbeError::Animation { source, .. } => {
+                Some(source.as_ref())
+            }
+            _ => None,
+        })
+    }
+
+    /// The innermost file the error happened in.
+    pub fn path(&self) -> Option<&Path> {
+        self.chain()
+            .filter_map(|err| match err {
+                FfbeError::File { path, .. } => Some(path.as_path()),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// The line of the file the error happened on, starting at 1.
+    pub fn line(&self) -> Option<usize> {
+        self.chain().find_map(|err| match err {
+            FfbeError::InvalidField { line, .. } | FfbeError::InvalidLine { line, .. } => {
+                Some(*line)
+            }
+            _ => None,
+        })
+    }
+
+    /// The name of the invalid field.
+    pub fn field(&self) -> Option<&'static str> {
+        self.chain().find_map(|err| match err {
+            FfbeError::InvalidField { field, .. } => Some(*field),
+            _ => None,
+        })
+    }
+
+    /// The unit and animation the error happened in.
+    pub fn animation(&self) -> Option<(u32, &str)> {
+        self.chain().find_map(|err| match err {
+            FfbeError::Animation {
+                unit_id, animation, ..
+            } => Some((*unit_id, animation.as_str())),
+            _ => None,
+        })
+    }
+}
 
 impl From<std::io::Error> for FfbeError {
     fn from(err: std::io::Error) -> Self {
@@ -53,7 +202,7 @@ impl From<image::ImageError> for FfbeError {
 
 impl From<APNGError> for FfbeError {
     fn from(err: APNGError) -> Self {
-        FfbeError::ParseError(err.to_string())
+        FfbeError::ApngError(err)
     }
 }
 
@@ -63,8 +212,56 @@ impl From<serde_json::Error> for FfbeError {
     }
 }
 
+impl From<toml::de::Error> for FfbeError {
+    fn from(err: toml::de::Error) -> Self {
+        FfbeError::ParseError(err.to_string())
+    }
+}
+
 impl From<ureq::Error> for FfbeError {
     fn from(err: ureq::Error) -> Self {
         FfbeError::UreqError(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_context_and_source_chain() {
+        let err = FfbeError::InvalidField {
+            line: 3,
+            field: "delay",
+            value: "x".to_string(),
+        }
+        .in_file("input/unit_atk_cgs_1.csv")
+        .in_animation(1, "atk");
+
+        assert_eq!(err.to_string(), "Failed to render animation atk of unit 1");
+        assert_eq!(
+            format!("{err:#}"),
+            "Failed to render animation atk of unit 1: Failed to read input/unit_atk_cgs_1.csv: \
+             Invalid delay value at line 3: 'x'"
+        );
+        assert_eq!(err.kind(), "parse");
+        assert_eq!(err.path(), Some(Path::new("input/unit_atk_cgs_1.csv")));
+        assert_eq!((err.line(), err.field()), (Some(3), Some("delay")));
+        assert_eq!(err.animation(), Some((1, "atk")));
+
+        let source = err.source().unwrap();
+        assert!(source.to_string().starts_with("Failed to read"));
+        assert!(source.source().unwrap().source().is_none());
+
+        let io = FfbeError::from(std::io::Error::other("disk full")).in_file("out.png");
+        assert_eq!(io.to_string(), "Failed to read out.png");
+        assert_eq!(
+            format!("{io:#}"),
+            "Failed to read out.png: IO error: disk full"
+        );
+        assert_eq!(io.source().unwrap().to_string(), "IO error: disk full");
+        assert!(io.source().unwrap().source().is_none());
+        assert_eq!(io.path(), Some(Path::new("out.png")));
+    }
+}
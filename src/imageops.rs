@@ -3,12 +3,15 @@ use crate::{
     constants::{ALPHA_TRANSPARENT_U8, DEFAULT_FPS, MAX_CHANNEL_F32, RGB_CHANNEL_COUNT},
     error,
 };
-use apng::{self, PNGImage, load_dynamic_image};
-use image::{self, ImageBuffer, Rgba};
+use apng::{self, PNGImage};
+use image::{self, ImageBuffer, Rgba, RgbaImage};
 use png;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -16,6 +19,18 @@ pub struct Rect {
     pub height: u32,
 }
 
+impl Rect {
+    /// Returns this rect with its position and size multiplied by `factor`.
+    pub fn scaled(self, factor: u32) -> Self {
+        Self {
+            x: self.x * factor as i32,
+            y: self.y * factor as i32,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Point(i32, i32);
 impl Point {
@@ -150,78 +165,242 @@ impl ColorBoundsExt for ImageBuffer<Rgba<u8>, Vec<u8>> {
     }
 }
 
-// Note: It's strange that we need to pass in a full
-// list of PNGImage in order to create the config when the `create_config` is
-// only using the first image in that list. This causes us to have to loop through
-// our frames twice.
-pub fn encode_animated_apng(frames: Vec<CompositeFrame>, output_path: &str) -> error::Result<()> {
-    let mut png_images: Vec<PNGImage> = Vec::new();
-    for frame in frames.clone() {
-        let fr_img = image::DynamicImage::from(frame.image);
-        let png_image = match load_dynamic_image(fr_img) {
-            Ok(png_image) => png_image,
-            Err(err) => {
-                let msg = "Failed to load frame image as png";
-                eprint!("{msg}: {err}");
-                return Err(crate::FfbeError::ParseError(msg.into()));
-            }
+/// Largest upscale factor; larger factors turn a typical frame into gigabytes of pixels.
+pub const MAX_SCALE: u32 = 16;
+
+/// Upscaling algorithm for pixel-art output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaler {
+    /// Nearest-neighbour; supports any integer factor up to [`MAX_SCALE`]
+    #[default]
+    Nearest,
+    /// AdvMAME Scale2x/Scale3x edge-smoothing; supports factors 2, 3 and 4
+    Scale2x,
+}
+
+impl Scaler {
+    pub fn supports(self, factor: u32) -> bool {
+        match self {
+            Scaler::Nearest => (1..=MAX_SCALE).contains(&factor),
+            Scaler::Scale2x => matches!(factor, 1..=4),
+        }
+    }
+}
+
+impl FromStr for Scaler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Scaler::Nearest),
+            "scale2x" | "scalex" | "epx" => Ok(Scaler::Scale2x),
+            _ => Err(format!(
+                "Invalid scaler '{s}': expected 'nearest' or 'scale2x'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Scaler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scaler::Nearest => write!(f, "nearest"),
+            Scaler::Scale2x => write!(f, "scale2x"),
+        }
+    }
+}
+
+/// Upscales an image by an integer `factor` with the given pixel-art scaler.
+pub fn upscale(img: &RgbaImage, factor: u32, scaler: Scaler) -> error::Result<RgbaImage> {
+    crate::validation::validate_scale(factor, scaler)?;
+
+    let (width, height) = img
+        .width()
+        .checked_mul(factor)
+        .zip(img.height().checked_mul(factor))
+        .ok_or_else(|| {
+            error::FfbeError::InvalidInput(format!(
+                "Upscaling a {}x{} image by {factor} exceeds the maximum image size",
+                img.width(),
+                img.height()
+            ))
+        })?;
+
+    let scaled = match (scaler, factor) {
+        (_, 1) => img.clone(),
+        (Scaler::Nearest, _) => {
+            image::imageops::resize(img, width, height, image::imageops::FilterType::Nearest)
+        }
+        (Scaler::Scale2x, 2) => scale2x(img),
+        (Scaler::Scale2x, 3) => scale3x(img),
+        (Scaler::Scale2x, _) => scale2x(&scale2x(img)),
+    };
+
+    Ok(scaled)
+}
+
+/// Returns the pixel at (x + dx, y + dy), clamped to the image edges.
+fn neighbour(img: &RgbaImage, x: u32, y: u32, dx: i32, dy: i32) -> Rgba<u8> {
+    let nx = (x as i32 + dx).clamp(0, img.width() as i32 - 1) as u32;
+    let ny = (y as i32 + dy).clamp(0, img.height() as i32 - 1) as u32;
+    *img.get_pixel(nx, ny)
+}
+
+/// AdvMAME Scale2x. Each pixel E becomes a 2x2 block, smoothing diagonal edges
+/// formed by its neighbours B (up), D (left), F (right) and H (down).
+fn scale2x(img: &RgbaImage) -> RgbaImage {
+    let mut out = RgbaImage::new(img.width() * 2, img.height() * 2);
+
+    for (x, y, &e) in img.enumerate_pixels() {
+        let b = neighbour(img, x, y, 0, -1);
+        let d = neighbour(img, x, y, -1, 0);
+        let f = neighbour(img, x, y, 1, 0);
+        let h = neighbour(img, x, y, 0, 1);
+
+        let (e0, e1, e2, e3) = if b != h && d != f {
+            (
+                if d == b { d } else { e },
+                if b == f { f } else { e },
+                if d == h { d } else { e },
+                if h == f { f } else { e },
+            )
+        } else {
+            (e, e, e, e)
         };
-        png_images.push(png_image);
+
+        out.put_pixel(x * 2, y * 2, e0);
+        out.put_pixel(x * 2 + 1, y * 2, e1);
+        out.put_pixel(x * 2, y * 2 + 1, e2);
+        out.put_pixel(x * 2 + 1, y * 2 + 1, e3);
     }
 
+    out
+}
+
+/// AdvMAME Scale3x. Each pixel E becomes a 3x3 block using the full 3x3 neighbourhood:
+/// A B C / D E F / G H I.
+fn scale3x(img: &RgbaImage) -> RgbaImage {
+    let mut out = RgbaImage::new(img.width() * 3, img.height() * 3);
+
+    for (x, y, &e) in img.enumerate_pixels() {
+        let a = neighbour(img, x, y, -1, -1);
+        let b = neighbour(img, x, y, 0, -1);
+        let c = neighbour(img, x, y, 1, -1);
+        let d = neighbour(img, x, y, -1, 0);
+        let f = neighbour(img, x, y, 1, 0);
+        let g = neighbour(img, x, y, -1, 1);
+        let h = neighbour(img, x, y, 0, 1);
+        let i = neighbour(img, x, y, 1, 1);
+
+        let block = if b != h && d != f {
+            [
+                if d == b { d } else { e },
+                if (d == b && e != c) || (b == f && e != a) {
+                    b
+                } else {
+                    e
+                },
+                if b == f { f } else { e },
+                if (d == b && e != g) || (d == h && e != a) {
+                    d
+                } else {
+                    e
+                },
+                e,
+                if (b == f && e != i) || (h == f && e != c) {
+                    f
+                } else {
+                    e
+                },
+                if d == h { d } else { e },
+                if (d == h && e != i) || (h == f && e != g) {
+                    h
+                } else {
+                    e
+                },
+                if h == f { f } else { e },
+            ]
+        } else {
+            [e; 9]
+        };
+
+        for (idx, pixel) in block.into_iter().enumerate() {
+            out.put_pixel(x * 3 + (idx % 3) as u32, y * 3 + (idx / 3) as u32, pixel);
+        }
+    }
+
+    out
+}
+
+/// Encodes the frames as an APNG, taking one frame at a time from `frames` so that the
+/// caller can build each full frame only when it is encoded.
+pub fn encode_animated_apng(
+    frames: impl ExactSizeIterator<Item = CompositeFrame>,
+    output_path: &str,
+) -> error::Result<()> {
+    let num_frames = frames.len();
+    let mut frames = frames.peekable();
+    let Some(first_frame) = frames.peek() else {
+        return Err(crate::FfbeError::InvalidInput(
+            "Cannot encode an APNG without frames".to_string(),
+        ));
+    };
+    // Same as `apng::create_config`, which would need every frame converted up front
+    let config = apng::Config {
+        width: first_frame.image.width(),
+        height: first_frame.image.height(),
+        num_frames: num_frames as u32,
+        num_plays: 0,
+        color: png::ColorType::Rgba,
+        depth: png::BitDepth::Eight,
+        filter: png::FilterType::NoFilter,
+    };
+
     let mut out = std::io::BufWriter::new(std::fs::File::create(output_path)?);
-    let config = apng::create_config(&png_images, None)?;
     let mut encoder = apng::Encoder::new(&mut out, config)?;
 
     for frame in frames {
+        let (width, height) = frame.image.dimensions();
         let png_image = PNGImage {
-            width: frame.image.width(),
-            height: frame.image.height(),
-            data: frame.image.as_raw().clone(),
+            width,
+            height,
+            data: Arc::unwrap_or_clone(frame.image).into_raw(),
             color_type: png::ColorType::Rgba,
-            bit_depth: png::BitDepth::Sixteen,
+            bit_depth: png::BitDepth::Eight,
         };
         let apng_frame = apng::Frame {
             delay_num: Some(frame.delay as u16), // Use frame's specific delay
             delay_den: Some(DEFAULT_FPS),        // Use constant instead of hardcoded 60
             ..Default::default()
         };
-        if let Err(err) = encoder.write_frame(&png_image, apng_frame) {
-            let msg = "Failed to write APNG frame";
-            eprint!("{msg}: {err}");
-            return Err(crate::FfbeError::ParseError(msg.into()));
-        }
+        encoder.write_frame(&png_image, apng_frame)?;
     }
 
-    match encoder.finish_encode() {
-        Ok(_) => {
-            println!("Successfully saved animated APNG: {output_path}");
-            Ok(())
-        }
-        Err(err) => {
-            eprintln!("Failed to save animated APNG: {err}");
-            Err(err.into())
-        }
-    }
+    encoder.finish_encode()?;
+    log::debug!("encoded {num_frames} frames as APNG {output_path}");
+    Ok(())
 }
 
-pub fn encode_animated_gif(frames: Vec<CompositeFrame>, output_path: &str) -> error::Result<()> {
-    let mut gif_frames = Vec::new();
-    for frame in frames {
-        let gif_frame = image::Frame::from_parts(
-            frame.image,
+/// Encodes the frames as a looping GIF, taking one frame at a time from `frames`.
+pub fn encode_animated_gif(
+    frames: impl ExactSizeIterator<Item = CompositeFrame>,
+    output_path: &str,
+) -> error::Result<()> {
+    let num_frames = frames.len();
+    let gif_frames = frames.map(|frame| {
+        image::Frame::from_parts(
+            Arc::unwrap_or_clone(frame.image),
             0,
             0,
             image::Delay::from_numer_denom_ms(frame.delay, DEFAULT_FPS as u32),
-        );
-        gif_frames.push(gif_frame);
-    }
+        )
+    });
     let mut buffer = std::io::BufWriter::new(std::fs::File::create(output_path)?);
     let mut encoder = image::codecs::gif::GifEncoder::new(&mut buffer);
     encoder.set_repeat(image::codecs::gif::Repeat::Infinite)?;
     encoder.encode_frames(gif_frames)?;
 
-    print!("Successfully saved animated gif: {output_path}");
+    log::debug!("encoded {num_frames} frames as GIF {output_path}");
 
     Ok(())
 }
@@ -342,6 +521,74 @@ mod tests {
         assert!(bounds.is_none());
     }
 
+    #[test]
+    fn test_rect_scaled() {
+        let rect = Rect {
+            x: -2,
+            y: 3,
+            width: 10,
+            height: 20,
+        }
+        .scaled(3);
+        assert_eq!(rect.x, -6);
+        assert_eq!(rect.y, 9);
+        assert_eq!(rect.width, 30);
+        assert_eq!(rect.height, 60);
+    }
+
+    #[test]
+    fn test_upscale_nearest() {
+        let mut img = RgbaImage::new(2, 1);
+        let red = Rgba([255, 0, 0, 255]);
+        img.put_pixel(0, 0, red);
+
+        let scaled = upscale(&img, 3, Scaler::Nearest).unwrap();
+        assert_eq!(scaled.dimensions(), (6, 3));
+        assert_eq!(*scaled.get_pixel(2, 2), red);
+        assert_eq!(*scaled.get_pixel(3, 0), Rgba([0, 0, 0, 0]));
+
+        assert!(upscale(&img, MAX_SCALE, Scaler::Nearest).is_ok());
+        assert!(upscale(&img, MAX_SCALE + 1, Scaler::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_upscale_scale2x_smooths_diagonals() {
+        // The transparent center pixel has red neighbours above and to the left,
+        // forming a diagonal edge across its top-left corner
+        let red = Rgba([255, 0, 0, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+        let mut img = RgbaImage::new(3, 3);
+        img.put_pixel(1, 0, red);
+        img.put_pixel(0, 1, red);
+
+        let nearest = upscale(&img, 2, Scaler::Nearest).unwrap();
+        let scaled = upscale(&img, 2, Scaler::Scale2x).unwrap();
+        assert_eq!(scaled.dimensions(), (6, 6));
+
+        // Nearest-neighbour leaves the whole center block transparent, scale2x fills
+        // in the corner touching the edge
+        assert_eq!(*nearest.get_pixel(2, 2), clear);
+        assert_eq!(*scaled.get_pixel(2, 2), red);
+        assert_eq!(*scaled.get_pixel(3, 2), clear);
+        assert_eq!(*scaled.get_pixel(2, 3), clear);
+        assert_eq!(*scaled.get_pixel(3, 3), clear);
+    }
+
+    #[test]
+    fn test_upscale_scale2x_factors() {
+        let img = RgbaImage::new(2, 3);
+        assert_eq!(
+            upscale(&img, 3, Scaler::Scale2x).unwrap().dimensions(),
+            (6, 9)
+        );
+        assert_eq!(
+            upscale(&img, 4, Scaler::Scale2x).unwrap().dimensions(),
+            (8, 12)
+        );
+        assert!(upscale(&img, 5, Scaler::Scale2x).is_err());
+        assert!(upscale(&img, 0, Scaler::Nearest).is_err());
+    }
+
     #[test]
     fn test_load_source_image_nonexistent() {
         let result = load_source_image(99999, "nonexistent_path");
@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+
+use crate::{
+    Result, Unit, cgg, cgs,
+    constants::{DEFAULT_FPS, HALF_CANVAS},
+    discovery,
+    imageops::{self, Rect},
+};
+
+/// Summary of a unit's cgg data, atlas and animations.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitInfo {
+    pub unit_id: u32,
+    /// Character name from the character database, when known.
+    pub name: Option<String>,
+    pub cgg_frames: usize,
+    pub cgg_parts: usize,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    /// Distinct atlas page ids referenced by the cgg parts.
+    pub atlas_pages: Vec<u32>,
+    pub animations: Vec<AnimationInfo>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationInfo {
+    pub name: String,
+    /// Number of cgs lines.
+    pub frames: usize,
+    pub duration_ms: u32,
+    pub distinct_cgg_frames: usize,
+    /// Bounds of the composited content relative to the origin, or `None` when every
+    /// frame is empty.
+    pub bounds: Option<Rect>,
+    pub blend_modes: Vec<i32>,
+    pub rotations: Vec<i32>,
+}
+
+impl UnitInfo {
+    /// Reads the unit's cgg file, atlas and every discovered cgs file from `input_dir`
+    /// and composites each animation to measure its bounds.
+    pub fn collect(unit_id: u32, input_dir: &str) -> Result<Self> {
+        let cgg_path = cgg::file_path(unit_id, input_dir);
+        let in_cgg_file = |err: crate::FfbeError| err.in_file(&cgg_path);
+        let cgg_text = fs::read_to_string(&cgg_path).map_err(|err| in_cgg_file(err.into()))?;
+        let mut frames = Vec::new();
+        for (row, line) in cgg_text.lines().enumerate() {
+            if let Some(parts) = cgg::process(line, row).map_err(in_cgg_file)? {
+                frames.push(parts);
+            }
+        }
+
+        let unit = Unit {
+            id: unit_id,
+            frames,
+            ..Default::default()
+        };
+        let src_img = imageops::load_source_image(unit_id, input_dir)?;
+        let cache = cgs::PartCache::new();
+
+        let animations = discovery::discover_animations(unit_id, input_dir)?
+            .into_iter()
+            .map(|animation| {
+                let in_cgs_file = |err: crate::FfbeError| err.in_file(&animation.file_path);
+                let cgs_text = fs::read_to_string(&animation.file_path)
+                    .map_err(|err| in_cgs_file(err.into()))?;
+                let cgs_frames = cgs_text
+                    .lines()
+                    .enumerate()
+                    .filter_map(|(row, line)| Some((row, cgs::process(line, row)?)))
+                    .map(|(row, meta)| {
+                        let cgs::CgsMeta(frame_idx, offset_x, offset_y, delay) =
+                            meta.map_err(in_cgs_file)?;
+                        cgs::check_frame_index(frame_idx, row, unit.frames.len())
+                            .map_err(in_cgs_file)?;
+                        Ok(cgs::Frame {
+                            frame_idx,
+                            parts: unit.frames[frame_idx].clone(),
+                            offset_x,
+                            offset_y,
+                            delay,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(AnimationInfo::new(
+                    animation.name,
+                    &cgs_frames,
+                    &unit,
+                    &src_img,
+                    &cache,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (atlas_width, atlas_height) = image::GenericImageView::dimensions(&src_img);
+        let all_parts = || unit.frames.iter().flatten();
+
+        Ok(Self {
+            unit_id,
+            name: None,
+            cgg_frames: unit.frames.len(),
+            cgg_parts: all_parts().count(),
+            atlas_width,
+            atlas_height,
+            atlas_pages: sorted_distinct(all_parts().map(|part| part.page_id)),
+            animations,
+        })
+    }
+}
+
+impl AnimationInfo {
+    fn new(
+        name: String,
+        frames: &[cgs::Frame],
+        unit: &Unit,
+        src_img: &image::DynamicImage,
+        cache: &cgs::PartCache,
+    ) -> Self {
+        let total_delay: u32 = frames.iter().map(|frame| frame.delay).sum();
+        let parts = || frames.iter().flat_map(|frame| frame.parts.iter());
+
+        let mut bounds_unit = unit.clone();
+        cgs::measure_frames(frames, src_img, &mut bounds_unit, cache);
+        let bounds = match (bounds_unit.top_left, bounds_unit.bottom_right) {
+            (Some(top_left), Some(bottom_right)) => Some(Rect {
+                x: top_left.x() - HALF_CANVAS as i32,
+                y: top_left.y() - HALF_CANVAS as i32,
+                width: (bottom_right.x() - top_left.x()) as u32,
+                height: (bottom_right.y() - top_left.y()) as u32,
+            }),
+            _ => None,
+        };
+
+        Self {
+            name,
+            frames: frames.len(),
+            duration_ms: total_delay * 1000 / DEFAULT_FPS as u32,
+            distinct_cgg_frames: sorted_distinct(frames.iter().map(|frame| frame.frame_idx)).len(),
+            bounds,
+            blend_modes: sorted_distinct(parts().map(|part| part.blend_mode)),
+            rotations: sorted_distinct(parts().map(|part| part.rotate)),
+        }
+    }
+}
+
+fn sorted_distinct<T: Ord>(values: impl Iterator<Item = T>) -> Vec<T> {
+    values.collect::<BTreeSet<T>>().into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_unit_info() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        for file in ["unit_anime_204000103.png", "unit_cgg_204000103.csv"] {
+            fs::copy(format!("test_data/{file}"), format!("{temp_path}/{file}")).unwrap();
+        }
+        fs::write(
+            format!("{temp_path}/unit_atk_cgs_204000103.csv"),
+            "32,0,0,30,\n33,0,0,15,\n32,0,0,15,\n",
+        )
+        .unwrap();
+
+        let info = UnitInfo::collect(204000103, temp_path).unwrap();
+
+        assert_eq!(info.unit_id, 204000103);
+        assert_eq!(info.name, None);
+        assert_eq!(info.cgg_frames, 107);
+        assert_eq!(info.cgg_parts, 741);
+        assert!(info.atlas_width > 0 && info.atlas_height > 0);
+        assert!(!info.atlas_pages.is_empty());
+
+        assert_eq!(info.animations.len(), 1);
+        let atk = &info.animations[0];
+        assert_eq!(atk.name, "atk");
+        assert_eq!(atk.frames, 3);
+        assert_eq!(atk.duration_ms, 1000);
+        assert_eq!(atk.distinct_cgg_frames, 2);
+        assert!(atk.bounds.is_some());
+        assert!(!atk.blend_modes.is_empty());
+        assert!(atk.rotations.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_collect_unit_info_missing_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert!(UnitInfo::collect(1, temp_dir.path().to_str().unwrap()).is_err());
+    }
+}
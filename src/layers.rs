@@ -0,0 +1,134 @@
+use image::{DynamicImage, RgbaImage, imageops::overlay};
+use std::path::Path;
+
+use crate::{Result, cgg, cgs::PartCache, debug::part_rect, imageops::Rect};
+
+/// A single cgg part drawn alone on a frame-sized transparent canvas.
+pub struct Layer {
+    pub name: String,
+    pub image: RgbaImage,
+}
+
+/// Names a layer so it sorts in draw order and can be traced back to its `PartData`:
+/// `{stack}_part{index}_{atlas_x}_{atlas_y}_{atlas_width}x{atlas_height}`, where `stack` is
+/// the position in draw order (bottom first) and `index` the part's index within its cgg line.
+pub fn layer_name(stack: usize, part: &cgg::PartData) -> String {
+    format!(
+        "{stack:02}_part{}_{}_{}_{}x{}",
+        part.index, part.atlas_x, part.atlas_y, part.atlas_width, part.atlas_height
+    )
+}
+
+/// Renders every part of a frame onto its own canvas the size of `frame_rect`, positioned
+/// exactly where it lands in the composited frame. Layers are returned in draw order.
+pub fn render_layers(
+    parts: &[cgg::PartData],
+    offset_x: i32,
+    offset_y: i32,
+    src_img: &DynamicImage,
+    cache: &PartCache,
+    frame_rect: Rect,
+) -> Vec<Layer> {
+    parts
+        .iter()
+        .enumerate()
+        .map(|(stack, part)| {
+            let part_img = cache.get_or_process(src_img, part);
+            let dest = part_rect(part, offset_x, offset_y);
+
+            let mut image = RgbaImage::new(frame_rect.width, frame_rect.height);
+            overlay(
+                &mut image,
+                part_img.as_ref(),
+                (dest.x - frame_rect.x) as i64,
+                (dest.y - frame_rect.y) as i64,
+            );
+
+            Layer {
+                name: layer_name(stack, part),
+                image,
+            }
+        })
+        .collect()
+}
+
+/// Writes each layer as `{name}.png` into `dir`, creating it if needed.
+pub fn save_layers(layers: &[Layer], dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for layer in layers {
+        layer.image.save(dir.join(format!("{}.png", layer.name)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use tempfile::TempDir;
+
+    fn part(index: usize, atlas_x: u32, canvas_x: i32) -> cgg::PartData {
+        cgg::PartData {
+            canvas_x,
+            canvas_y: 0,
+            opacity: 100,
+            atlas_x,
+            atlas_y: 0,
+            atlas_width: 2,
+            atlas_height: 2,
+            index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_layer_name() {
+        let part = cgg::PartData {
+            atlas_x: 12,
+            atlas_y: 34,
+            atlas_width: 56,
+            atlas_height: 78,
+            index: 3,
+            ..Default::default()
+        };
+        assert_eq!(layer_name(1, &part), "01_part3_12_34_56x78");
+    }
+
+    #[test]
+    fn test_render_and_save_layers() {
+        let mut atlas = RgbaImage::new(4, 2);
+        for y in 0..2 {
+            atlas.put_pixel(0, y, Rgba([255, 0, 0, 255]));
+            atlas.put_pixel(1, y, Rgba([255, 0, 0, 255]));
+            atlas.put_pixel(2, y, Rgba([0, 0, 255, 255]));
+            atlas.put_pixel(3, y, Rgba([0, 0, 255, 255]));
+        }
+        let src_img = DynamicImage::ImageRgba8(atlas);
+        let cache = PartCache::new();
+        let frame_rect = Rect {
+            x: 995,
+            y: 995,
+            width: 10,
+            height: 10,
+        };
+
+        let parts = vec![part(1, 0, -3), part(0, 2, 2)];
+        let layers = render_layers(&parts, 1, 0, &src_img, &cache, frame_rect);
+
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].name, "00_part1_0_0_2x2");
+        assert_eq!(layers[0].image.dimensions(), (10, 10));
+        // First part lands at canvas (998, 1000) → local (3, 5)
+        assert_eq!(*layers[0].image.get_pixel(3, 5), Rgba([255, 0, 0, 255]));
+        assert_eq!(layers[0].image.get_pixel(8, 5).0[3], 0);
+        // Second part lands at canvas (1003, 1000) → local (8, 5), alone on its layer
+        assert_eq!(*layers[1].image.get_pixel(8, 5), Rgba([0, 0, 255, 255]));
+        assert_eq!(layers[1].image.get_pixel(3, 5).0[3], 0);
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("000");
+        save_layers(&layers, &dir).unwrap();
+        assert!(dir.join("00_part1_0_0_2x2.png").exists());
+        assert!(dir.join("01_part0_2_0_2x2.png").exists());
+    }
+}
@@ -0,0 +1,459 @@
+use crate::{
+    cgs::CompositeFrame,
+    constants::{FRAME_PADDING, MAX_CHANNEL_U8},
+    imageops::{Point, Rect},
+};
+use image::{Rgba, RgbaImage, imageops};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Default checkerboard square size in pixels
+pub const DEFAULT_CHECKER_SIZE: u32 = 8;
+const CHECKER_LIGHT: Rgba<u8> = Rgba([0xFF, 0xFF, 0xFF, MAX_CHANNEL_U8]);
+const CHECKER_DARK: Rgba<u8> = Rgba([0xCC, 0xCC, 0xCC, MAX_CHANNEL_U8]);
+
+/// Padding added around the content bounds of every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Padding {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl Default for Padding {
+    /// Matches the historical behaviour of padding only the right and bottom edges.
+    fn default() -> Self {
+        Self {
+            top: 0,
+            right: FRAME_PADDING,
+            bottom: FRAME_PADDING,
+            left: 0,
+        }
+    }
+}
+
+impl FromStr for Padding {
+    type Err = String;
+
+    /// Parses CSS-style shorthand: `all`, `vertical,horizontal` or `top,right,bottom,left`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("Invalid padding '{s}': expected non-negative integers"))?;
+
+        match values[..] {
+            [all] => Ok(Self {
+                top: all,
+                right: all,
+                bottom: all,
+                left: all,
+            }),
+            [vertical, horizontal] => Ok(Self {
+                top: vertical,
+                right: horizontal,
+                bottom: vertical,
+                left: horizontal,
+            }),
+            [top, right, bottom, left] => Ok(Self {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            _ => Err(format!(
+                "Invalid padding '{s}': expected 1, 2 or 4 comma separated values"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.top, self.right, self.bottom, self.left
+        )
+    }
+}
+
+/// Rounding applied to the frame cell size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellAlign {
+    #[default]
+    None,
+    /// Round up to a multiple of the given number of pixels
+    Multiple(u32),
+    /// Round up to the next power of two
+    PowerOfTwo,
+}
+
+impl CellAlign {
+    pub fn align(self, value: u32) -> u32 {
+        match self {
+            CellAlign::None => value,
+            CellAlign::Multiple(n) => value.div_ceil(n) * n,
+            CellAlign::PowerOfTwo => value.next_power_of_two(),
+        }
+    }
+}
+
+impl FromStr for CellAlign {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(CellAlign::None),
+            "pow2" => Ok(CellAlign::PowerOfTwo),
+            _ => match s.parse::<u32>() {
+                Ok(0) | Err(_) => Err(format!(
+                    "Invalid cell alignment '{s}': expected 'none', 'pow2' or a positive integer"
+                )),
+                Ok(n) => Ok(CellAlign::Multiple(n)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CellAlign {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellAlign::None => write!(f, "none"),
+            CellAlign::Multiple(n) => write!(f, "{n}"),
+            CellAlign::PowerOfTwo => write!(f, "pow2"),
+        }
+    }
+}
+
+/// Background drawn behind frames in spritesheets and animated outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    #[default]
+    Transparent,
+    Solid(Rgba<u8>),
+    /// Alternating light and dark squares of the given size
+    Checkerboard(u32),
+}
+
+impl Background {
+    pub fn is_transparent(self) -> bool {
+        matches!(self, Background::Transparent)
+    }
+
+    /// Creates an image of the given size filled with this background.
+    pub fn canvas(self, width: u32, height: u32) -> RgbaImage {
+        match self {
+            Background::Transparent => RgbaImage::new(width, height),
+            Background::Solid(color) => RgbaImage::from_pixel(width, height, color),
+            Background::Checkerboard(size) => RgbaImage::from_fn(width, height, |x, y| {
+                if (x / size + y / size) % 2 == 0 {
+                    CHECKER_LIGHT
+                } else {
+                    CHECKER_DARK
+                }
+            }),
+        }
+    }
+
+    /// Returns the full frame of an animated output: the frame's content drawn at its
+    /// position in `cell_rect` over this background. Built for one frame at a time so that
+    /// only the content of the other frames is held in memory.
+    pub fn place_frame(self, frame: &CompositeFrame, cell_rect: Rect) -> CompositeFrame {
+        let mut canvas = self.canvas(cell_rect.width, cell_rect.height);
+        let (x, y) = (
+            (frame.rect.x - cell_rect.x) as i64,
+            (frame.rect.y - cell_rect.y) as i64,
+        );
+        if self.is_transparent() {
+            imageops::replace(&mut canvas, frame.image.as_ref(), x, y);
+        } else {
+            imageops::overlay(&mut canvas, frame.image.as_ref(), x, y);
+        }
+
+        CompositeFrame {
+            image: Arc::new(canvas),
+            rect: cell_rect,
+            ..frame.clone()
+        }
+    }
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    /// Parses `transparent`, `#RRGGBB`, `#RRGGBBAA`, `checkerboard` or `checkerboard:SIZE`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "transparent" {
+            return Ok(Background::Transparent);
+        }
+
+        if let Some(rest) = s.strip_prefix("checkerboard") {
+            return match rest.strip_prefix(':') {
+                None if rest.is_empty() => Ok(Background::Checkerboard(DEFAULT_CHECKER_SIZE)),
+                Some(size) => match size.parse::<u32>() {
+                    Ok(size) if size > 0 => Ok(Background::Checkerboard(size)),
+                    _ => Err(format!("Invalid checkerboard size in '{s}'")),
+                },
+                None => Err(format!("Invalid background '{s}'")),
+            };
+        }
+
+        let hex = s
+            .strip_prefix('#')
+            .ok_or_else(|| format!("Invalid background '{s}'"))?;
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| format!("Invalid colour '{s}': expected #RRGGBB or #RRGGBBAA"))
+        };
+
+        match hex.len() {
+            6 => Ok(Background::Solid(Rgba([
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+                MAX_CHANNEL_U8,
+            ]))),
+            8 => Ok(Background::Solid(Rgba([
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+                channel(6)?,
+            ]))),
+            _ => Err(format!(
+                "Invalid colour '{s}': expected #RRGGBB or #RRGGBBAA"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Background::Transparent => write!(f, "transparent"),
+            Background::Solid(Rgba([r, g, b, a])) => write!(f, "#{r:02x}{g:02x}{b:02x}{a:02x}"),
+            Background::Checkerboard(size) => write!(f, "checkerboard:{size}"),
+        }
+    }
+}
+
+/// Controls how frame cells are sized and arranged in the outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayoutOptions {
+    pub padding: Padding,
+    pub cell_align: CellAlign,
+    /// Gap in pixels between spritesheet cells
+    pub spacing: u32,
+    pub background: Background,
+}
+
+impl LayoutOptions {
+    /// Returns the frame cell for content spanning `top_left` to `bottom_right` on the canvas.
+    ///
+    /// Left/top padding moves the cell origin; right/bottom padding and any cell alignment
+    /// grow the cell so the content keeps its position.
+    pub fn frame_rect(&self, top_left: Point, bottom_right: Point) -> Rect {
+        let Padding {
+            top,
+            right,
+            bottom,
+            left,
+        } = self.padding;
+        let content_width = (bottom_right.x() - top_left.x()) as u32;
+        let content_height = (bottom_right.y() - top_left.y()) as u32;
+
+        Rect {
+            x: top_left.x() - left as i32,
+            y: top_left.y() - top as i32,
+            width: self.cell_align.align(left + content_width + right),
+            height: self.cell_align.align(top + content_height + bottom),
+        }
+    }
+
+    /// Returns the top-left position of cell `index` in a sheet with `columns` columns.
+    pub fn cell_position(&self, index: usize, columns: usize, frame_rect: Rect) -> (u32, u32) {
+        let column = (index % columns) as u32;
+        let row = (index / columns) as u32;
+        (
+            column * (frame_rect.width + self.spacing),
+            row * (frame_rect.height + self.spacing),
+        )
+    }
+
+    /// Returns the sheet dimensions for a grid of `columns` x `rows` cells.
+    pub fn sheet_size(&self, columns: u32, rows: u32, frame_rect: Rect) -> (u32, u32) {
+        let gaps = |cells: u32| cells.saturating_sub(1) * self.spacing;
+        (
+            columns * frame_rect.width + gaps(columns),
+            rows * frame_rect.height + gaps(rows),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padding_default_matches_frame_padding() {
+        let padding = Padding::default();
+        assert_eq!(padding.top, 0);
+        assert_eq!(padding.left, 0);
+        assert_eq!(padding.right, FRAME_PADDING);
+        assert_eq!(padding.bottom, FRAME_PADDING);
+    }
+
+    #[test]
+    fn test_padding_from_str() {
+        assert_eq!(
+            "4".parse::<Padding>().unwrap(),
+            Padding {
+                top: 4,
+                right: 4,
+                bottom: 4,
+                left: 4
+            }
+        );
+        assert_eq!(
+            "2,6".parse::<Padding>().unwrap(),
+            Padding {
+                top: 2,
+                right: 6,
+                bottom: 2,
+                left: 6
+            }
+        );
+        assert_eq!(
+            "1,2,3,4".parse::<Padding>().unwrap(),
+            Padding {
+                top: 1,
+                right: 2,
+                bottom: 3,
+                left: 4
+            }
+        );
+        assert!("1,2,3".parse::<Padding>().is_err());
+        assert!("-1".parse::<Padding>().is_err());
+    }
+
+    #[test]
+    fn test_cell_align() {
+        assert_eq!(CellAlign::None.align(13), 13);
+        assert_eq!(CellAlign::Multiple(8).align(13), 16);
+        assert_eq!(CellAlign::Multiple(8).align(16), 16);
+        assert_eq!(CellAlign::PowerOfTwo.align(100), 128);
+        assert_eq!("8".parse::<CellAlign>().unwrap(), CellAlign::Multiple(8));
+        assert_eq!("pow2".parse::<CellAlign>().unwrap(), CellAlign::PowerOfTwo);
+        assert!("0".parse::<CellAlign>().is_err());
+    }
+
+    #[test]
+    fn test_background_from_str() {
+        assert_eq!(
+            "transparent".parse::<Background>().unwrap(),
+            Background::Transparent
+        );
+        assert_eq!(
+            "#ff8000".parse::<Background>().unwrap(),
+            Background::Solid(Rgba([255, 128, 0, 255]))
+        );
+        assert_eq!(
+            "#ff800080".parse::<Background>().unwrap(),
+            Background::Solid(Rgba([255, 128, 0, 128]))
+        );
+        assert_eq!(
+            "checkerboard".parse::<Background>().unwrap(),
+            Background::Checkerboard(DEFAULT_CHECKER_SIZE)
+        );
+        assert_eq!(
+            "checkerboard:16".parse::<Background>().unwrap(),
+            Background::Checkerboard(16)
+        );
+        assert!("#fff".parse::<Background>().is_err());
+        assert!("checkerboard:0".parse::<Background>().is_err());
+        assert!("blue".parse::<Background>().is_err());
+    }
+
+    #[test]
+    fn test_background_canvas_checkerboard() {
+        let canvas = Background::Checkerboard(2).canvas(4, 4);
+        assert_eq!(*canvas.get_pixel(0, 0), CHECKER_LIGHT);
+        assert_eq!(*canvas.get_pixel(2, 0), CHECKER_DARK);
+        assert_eq!(*canvas.get_pixel(2, 2), CHECKER_LIGHT);
+    }
+
+    #[test]
+    fn test_frame_rect_with_padding_and_alignment() {
+        let layout = LayoutOptions {
+            padding: Padding {
+                top: 2,
+                right: 3,
+                bottom: 4,
+                left: 5,
+            },
+            cell_align: CellAlign::Multiple(8),
+            ..Default::default()
+        };
+
+        let rect = layout.frame_rect(Point::new(100, 200), Point::new(150, 230));
+        assert_eq!(rect.x, 95);
+        assert_eq!(rect.y, 198);
+        assert_eq!(rect.width, 64); // 5 + 50 + 3 = 58 -> 64
+        assert_eq!(rect.height, 40); // 2 + 30 + 4 = 36 -> 40
+    }
+
+    #[test]
+    fn test_sheet_layout_with_spacing() {
+        let layout = LayoutOptions {
+            spacing: 4,
+            ..Default::default()
+        };
+        let frame_rect = Rect {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 20,
+        };
+
+        assert_eq!(layout.sheet_size(3, 2, frame_rect), (38, 44));
+        assert_eq!(layout.cell_position(4, 3, frame_rect), (14, 24));
+    }
+
+    #[test]
+    fn test_place_frame_on_background() {
+        let frame = CompositeFrame {
+            frame_idx: 0,
+            offset_x: 0,
+            offset_y: 0,
+            image: Arc::new(RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]))),
+            rect: Rect {
+                x: 12,
+                y: 11,
+                width: 1,
+                height: 1,
+            },
+            delay: 1,
+        };
+        let cell_rect = Rect {
+            x: 10,
+            y: 10,
+            width: 4,
+            height: 3,
+        };
+
+        let background = Background::Solid(Rgba([10, 20, 30, 255]));
+        let placed = background.place_frame(&frame, cell_rect);
+
+        assert_eq!(placed.image.dimensions(), (4, 3));
+        assert_eq!(placed.rect, cell_rect);
+        assert_eq!(*placed.image.get_pixel(2, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*placed.image.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+
+        let placed = Background::Transparent.place_frame(&frame, cell_rect);
+        assert_eq!(placed.image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+    }
+}
@@ -1,12 +1,25 @@
+pub mod atlas;
 pub mod cgg;
 pub mod cgs;
 pub mod character_db;
+pub mod config;
 pub mod constants;
+pub mod debug;
 pub mod discovery;
 pub mod error;
 pub mod imageops;
+pub mod info;
+pub mod layers;
+pub mod layout;
+pub mod lint;
+pub mod manifest;
 pub mod metadata;
+pub mod output;
+pub mod progress;
+pub mod render;
+pub mod report;
 pub mod validation;
+pub mod watch;
 
 pub use error::{FfbeError, Result};
 
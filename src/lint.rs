@@ -0,0 +1,274 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::{Result, cgg, cgs, discovery};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Unusual data that still renders.
+    Warning,
+    /// Data that fails to load or renders incorrectly.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in one of a unit's files.
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub file: String,
+    /// 1-based line number, when the problem is tied to a line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}: {}:{}: {}",
+                self.severity, self.file, line, self.message
+            ),
+            None => write!(f, "{}: {}: {}", self.severity, self.file, self.message),
+        }
+    }
+}
+
+impl Issue {
+    fn new(severity: Severity, file: &str, line: Option<usize>, message: String) -> Self {
+        Self {
+            severity,
+            file: file.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+/// Checks the contents of a cgg file. Returns the number of frames it defines, which is
+/// what cgs frame indices refer to, along with any issues found.
+///
+/// Part rectangles are checked against `atlas_size` when the atlas dimensions are known.
+pub fn lint_cgg(text: &str, file: &str, atlas_size: Option<(u32, u32)>) -> (usize, Vec<Issue>) {
+    let mut frame_count = 0;
+    let mut issues = Vec::new();
+
+    for (row, line) in text.lines().enumerate() {
+        let issue = |severity, message: String| Issue::new(severity, file, Some(row + 1), message);
+
+        let parts = match cgg::process(line, row) {
+            Ok(Some(parts)) => parts,
+            Ok(None) => continue,
+            Err(err) => {
+                issues.push(issue(Severity::Error, err.to_string()));
+                continue;
+            }
+        };
+        frame_count += 1;
+
+        for part in &parts {
+            let index = part.index;
+            if !matches!(part.rotate, 0 | 90 | -90 | 180 | -180 | 270 | -270) {
+                issues.push(issue(
+                    Severity::Warning,
+                    format!("part {index} has unsupported rotation {}", part.rotate),
+                ));
+            }
+            if !matches!(part.blend_mode, 0 | 1) {
+                issues.push(issue(
+                    Severity::Warning,
+                    format!("part {index} has unknown blend mode {}", part.blend_mode),
+                ));
+            }
+            if !(0..=100).contains(&part.opacity) {
+                issues.push(issue(
+                    Severity::Warning,
+                    format!("part {index} has opacity {} outside 0-100", part.opacity),
+                ));
+            }
+            if part.atlas_width == 0 || part.atlas_height == 0 {
+                issues.push(issue(
+                    Severity::Warning,
+                    format!("part {index} has an empty atlas rectangle"),
+                ));
+            }
+            if let Some((atlas_width, atlas_height)) = atlas_size
+                && (part.atlas_x + part.atlas_width > atlas_width
+                    || part.atlas_y + part.atlas_height > atlas_height)
+            {
+                issues.push(issue(
+                    Severity::Error,
+                    format!(
+                        "part {index} atlas rectangle {},{} {}x{} extends past the {atlas_width}x{atlas_height} atlas",
+                        part.atlas_x, part.atlas_y, part.atlas_width, part.atlas_height
+                    ),
+                ));
+            }
+        }
+    }
+
+    (frame_count, issues)
+}
+
+/// Checks the contents of a cgs file against a cgg file defining `frame_count` frames.
+pub fn lint_cgs(text: &str, file: &str, frame_count: usize) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (row, line) in text.lines().enumerate() {
+        let issue = |severity, message: String| Issue::new(severity, file, Some(row + 1), message);
+
+        match cgs::process(line, row) {
+            Some(Ok(cgs::CgsMeta(frame_idx, _, _, delay))) => {
+                if let Err(err) = cgs::check_frame_index(frame_idx, row, frame_count) {
+                    issues.push(issue(Severity::Error, err.to_string()));
+                }
+                if delay == 0 {
+                    issues.push(issue(
+                        Severity::Warning,
+                        "frame has a delay of 0".to_string(),
+                    ));
+                }
+            }
+            Some(Err(err)) => issues.push(issue(Severity::Error, err.to_string())),
+            None => continue,
+        }
+    }
+
+    issues
+}
+
+/// Checks a unit's atlas, cgg file and every discovered cgs file in `input_dir`.
+pub fn lint_unit(unit_id: u32, input_dir: &str) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+
+    let atlas_file = format!("{input_dir}/unit_anime_{unit_id}.png");
+    let atlas_size = match image::image_dimensions(&atlas_file) {
+        Ok(size) => Some(size),
+        Err(err) => {
+            issues.push(Issue::new(
+                Severity::Error,
+                &atlas_file,
+                None,
+                format!("cannot read atlas: {err}"),
+            ));
+            None
+        }
+    };
+
+    let cgg_file = format!("{input_dir}/unit_cgg_{unit_id}.csv");
+    if !Path::new(&cgg_file).exists() {
+        issues.push(Issue::new(
+            Severity::Error,
+            &cgg_file,
+            None,
+            "cgg file not found".to_string(),
+        ));
+        return Ok(issues);
+    }
+    let (frame_count, cgg_issues) =
+        lint_cgg(&std::fs::read_to_string(&cgg_file)?, &cgg_file, atlas_size);
+    issues.extend(cgg_issues);
+
+    match discovery::discover_animations(unit_id, input_dir) {
+        Ok(animations) => {
+            for animation in animations {
+                let text = std::fs::read_to_string(&animation.file_path)?;
+                issues.extend(lint_cgs(&text, &animation.file_path, frame_count));
+            }
+        }
+        Err(err) => issues.push(Issue::new(
+            Severity::Warning,
+            input_dir,
+            None,
+            format!("{err:#}"),
+        )),
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // anchor, count, then 11 fields per part:
+    // canvas_x, canvas_y, next_type, blend_mode, opacity, rotate, atlas_x, atlas_y, w, h, page
+    const VALID_PART: &str = "0,0,0,0,100,0,0,0,8,8,0";
+
+    #[test]
+    fn test_lint_cgg_valid() {
+        let text = format!("0,1,{VALID_PART}\n\n0,1,{VALID_PART}\n");
+        let (frame_count, issues) = lint_cgg(&text, "unit_cgg_1.csv", Some((16, 16)));
+
+        assert_eq!(frame_count, 2);
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_lint_cgg_reports_part_problems() {
+        let text = "0,1,0,0,0,2,150,45,10,10,8,8,0\n0,1,x,0,0,0,100,0,0,0,8,8,0\n";
+        let (frame_count, issues) = lint_cgg(text, "unit_cgg_1.csv", Some((16, 16)));
+
+        assert_eq!(frame_count, 1);
+        let messages: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert!(messages.contains(
+            &"warning: unit_cgg_1.csv:1: part 0 has unsupported rotation 45".to_string()
+        ));
+        assert!(messages.iter().any(|m| m.contains("unknown blend mode 2")));
+        assert!(messages.iter().any(|m| m.contains("opacity 150")));
+        assert!(
+            issues
+                .iter()
+                .any(|i| i.severity == Severity::Error && i.message.contains("extends past"))
+        );
+        assert!(
+            issues
+                .iter()
+                .any(|i| i.severity == Severity::Error && i.line == Some(2))
+        );
+    }
+
+    #[test]
+    fn test_lint_cgs() {
+        let text = "0,0,0,2\n3,0,0,2\n1,0,0,0\nx,0,0,2\n";
+        let issues = lint_cgs(text, "unit_atk_cgs_1.csv", 2);
+
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].line, Some(2));
+        assert!(issues[0].message.contains("cgg frame 3"));
+        assert_eq!(issues[1].severity, Severity::Warning);
+        assert_eq!(issues[1].line, Some(3));
+        assert_eq!(issues[2].severity, Severity::Error);
+        assert_eq!(issues[2].line, Some(4));
+    }
+
+    #[test]
+    fn test_lint_unit_fixture() {
+        let issues = lint_unit(204000103, "test_data").unwrap();
+        assert!(
+            issues.iter().all(|i| i.severity != Severity::Error),
+            "{issues:?}"
+        );
+    }
+
+    #[test]
+    fn test_lint_unit_missing_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let issues = lint_unit(1, temp_dir.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.severity == Severity::Error));
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::{FfbeError, Result};
+
+/// File name of the manifest kept in the output directory.
+pub const MANIFEST_FILE: &str = ".ffbetool-manifest.json";
+
+/// Hashes of the inputs each animation in an output directory was last rendered from.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Input hash keyed by [`Manifest::key`].
+    #[serde(default)]
+    pub animations: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Loads the manifest of `output_dir`, or an empty manifest if there is none yet.
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents =
+            std::fs::read_to_string(&path).map_err(|err| FfbeError::from(err).in_file(&path))?;
+        serde_json::from_str(&contents).map_err(|err| FfbeError::from(err).in_file(&path))
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        crate::output::write_atomic(&output_dir.join(MANIFEST_FILE), |path| {
+            Ok(std::fs::write(path, json)?)
+        })
+    }
+
+    pub fn key(unit_id: u32, anim_name: &str) -> String {
+        format!("{unit_id}/{anim_name}")
+    }
+
+    /// Whether the animation was last rendered from inputs with this hash.
+    pub fn is_current(&self, key: &str, hash: &str) -> bool {
+        self.animations
+            .get(key)
+            .is_some_and(|recorded| recorded == hash)
+    }
+
+    pub fn record(&mut self, key: String, hash: String) {
+        self.animations.insert(key, hash);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.animations.remove(key);
+    }
+}
+
+/// Builds a SHA-256 hash over input files and render options. The ffbetool version is
+/// always included so that upgrading re-renders everything.
+#[derive(Clone)]
+pub struct InputHasher(Sha256);
+
+impl Default for InputHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputHasher {
+    pub fn new() -> Self {
+        let mut hasher = Self(Sha256::new());
+        hasher.add_str("version", env!("CARGO_PKG_VERSION"));
+        hasher
+    }
+
+    /// Adds a labelled value. Labels and values are length-prefixed so that adjacent
+    /// entries cannot run into each other.
+    pub fn add_str(&mut self, label: &str, value: &str) {
+        self.add_bytes(label, value.as_bytes());
+    }
+
+    /// Adds the contents of a file, labelled with its file name.
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let contents = std::fs::read(path)?;
+        let label = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.add_bytes(&label, &contents);
+        Ok(())
+    }
+
+    fn add_bytes(&mut self, label: &str, bytes: &[u8]) {
+        for chunk in [label.as_bytes(), bytes] {
+            self.0.update((chunk.len() as u64).to_le_bytes());
+            self.0.update(chunk);
+        }
+    }
+
+    /// Returns the hash as lowercase hex.
+    pub fn finish(self) -> String {
+        self.0
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_depends_on_labels_values_and_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("unit_atk_cgs_1.csv");
+        std::fs::write(&file, "0,0,0,2\n").unwrap();
+
+        let hash = |options: &str| {
+            let mut hasher = InputHasher::new();
+            hasher.add_str("options", options);
+            hasher.add_file(&file).unwrap();
+            hasher.finish()
+        };
+
+        let original = hash("columns=0");
+        assert_eq!(original.len(), 64);
+        assert_eq!(original, hash("columns=0"));
+        assert_ne!(original, hash("columns=4"));
+
+        std::fs::write(&file, "0,0,0,3\n").unwrap();
+        assert_ne!(original, hash("columns=0"));
+
+        let mut split = InputHasher::new();
+        split.add_str("ab", "c");
+        let mut joined = InputHasher::new();
+        joined.add_str("a", "bc");
+        assert_ne!(split.finish(), joined.finish());
+
+        assert!(
+            InputHasher::new()
+                .add_file(&temp_dir.path().join("missing.csv"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let mut manifest = Manifest::load(temp_dir.path()).unwrap();
+        assert!(manifest.animations.is_empty());
+
+        let key = Manifest::key(123, "atk");
+        assert_eq!(key, "123/atk");
+        manifest.record(key.clone(), "abc".to_string());
+        manifest.save(temp_dir.path()).unwrap();
+
+        let mut manifest = Manifest::load(temp_dir.path()).unwrap();
+        assert!(manifest.is_current(&key, "abc"));
+        assert!(!manifest.is_current(&key, "def"));
+        assert!(!manifest.is_current("123/idle", "abc"));
+
+        manifest.remove(&key);
+        assert!(!manifest.is_current(&key, "abc"));
+
+        std::fs::write(temp_dir.path().join(MANIFEST_FILE), "not json").unwrap();
+        assert!(Manifest::load(temp_dir.path()).is_err());
+    }
+}
@@ -1,6 +1,38 @@
-use crate::{Result, cgs, imageops};
+use crate::{Result, cgs, constants::HALF_CANVAS, imageops};
 use serde::{Deserialize, Serialize};
 
+/// Position of the game's origin (the pivot used to place the unit on the battlefield)
+/// within a cropped frame, both in pixels and normalized to the frame size.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Origin {
+    pub x: i32,
+    pub y: i32,
+    pub normalized_x: f32,
+    pub normalized_y: f32,
+}
+
+impl Origin {
+    /// Returns where the canvas origin (`HALF_CANVAS`, `HALF_CANVAS`) shifted by the given
+    /// cgs offset ends up once frames are cropped to `frame_rect`.
+    pub fn new(frame_rect: imageops::Rect, offset_x: i32, offset_y: i32) -> Self {
+        let x = HALF_CANVAS as i32 + offset_x - frame_rect.x;
+        let y = HALF_CANVAS as i32 + offset_y - frame_rect.y;
+
+        Self {
+            x,
+            y,
+            normalized_x: x as f32 / frame_rect.width as f32,
+            normalized_y: y as f32 / frame_rect.height as f32,
+        }
+    }
+
+    /// The unit's origin before any per-frame cgs offset is applied.
+    pub fn from_frame_rect(frame_rect: imageops::Rect) -> Self {
+        Self::new(frame_rect, 0, 0)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AnimationJson {
@@ -10,6 +42,30 @@ pub struct AnimationJson {
     pub frame_rect: imageops::Rect,
     pub image_width: u32,
     pub image_height: u32,
+    pub origin: Origin,
+    /// The origin of each frame, including that frame's cgs offset.
+    pub frame_origins: Vec<Origin>,
+    /// Gap in pixels between spritesheet cells.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cell_spacing: u32,
+    /// Upscale factor the outputs were rendered at.
+    #[serde(default = "default_scale", skip_serializing_if = "is_one")]
+    pub scale: u32,
+    /// The bounds every animation of the unit was cropped to with `--shared-bounds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_bounds: Option<imageops::Rect>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_one(value: &u32) -> bool {
+    *value == 1
+}
+
+fn default_scale() -> u32 {
+    1
 }
 
 impl AnimationJson {
@@ -22,6 +78,10 @@ impl AnimationJson {
         spritesheet_height: u32,
     ) -> Self {
         let frame_delays = frames.iter().map(|f| f.delay).collect();
+        let frame_origins = frames
+            .iter()
+            .map(|f| Origin::new(frame_rect, f.offset_x, f.offset_y))
+            .collect();
 
         Self {
             unit_id,
@@ -30,8 +90,25 @@ impl AnimationJson {
             frame_rect,
             image_width: spritesheet_width,
             image_height: spritesheet_height,
+            origin: Origin::from_frame_rect(frame_rect),
+            frame_origins,
+            cell_spacing: 0,
+            scale: 1,
+            shared_bounds: None,
         }
     }
+
+    /// Scales every rect and origin by `factor` to match outputs upscaled by that factor.
+    /// Normalized origins are unaffected.
+    pub fn scale(&mut self, factor: u32) {
+        self.frame_rect = self.frame_rect.scaled(factor);
+        self.shared_bounds = self.shared_bounds.map(|rect| rect.scaled(factor));
+        for origin in std::iter::once(&mut self.origin).chain(self.frame_origins.iter_mut()) {
+            origin.x *= factor as i32;
+            origin.y *= factor as i32;
+        }
+        self.scale *= factor;
+    }
 }
 
 pub fn save_animation_json(animation_json: &AnimationJson, output_path: &str) -> Result<()> {
@@ -50,7 +127,9 @@ mod tests {
         let frames = vec![
             cgs::CompositeFrame {
                 frame_idx: 0,
-                image: image::RgbaImage::new(50, 50),
+                offset_x: 0,
+                offset_y: 0,
+                image: image::RgbaImage::new(50, 50).into(),
                 rect: Rect {
                     x: 10,
                     y: 20,
@@ -61,7 +140,9 @@ mod tests {
             },
             cgs::CompositeFrame {
                 frame_idx: 1,
-                image: image::RgbaImage::new(50, 50),
+                offset_x: 0,
+                offset_y: 0,
+                image: image::RgbaImage::new(50, 50).into(),
                 rect: Rect {
                     x: 10,
                     y: 20,
@@ -72,7 +153,9 @@ mod tests {
             },
             cgs::CompositeFrame {
                 frame_idx: 2,
-                image: image::RgbaImage::new(50, 50),
+                offset_x: 0,
+                offset_y: 0,
+                image: image::RgbaImage::new(50, 50).into(),
                 rect: Rect {
                     x: 10,
                     y: 20,
@@ -124,6 +207,16 @@ mod tests {
             },
             image_width: 752,
             image_height: 1344,
+            origin: Origin {
+                x: 98,
+                y: 127,
+                normalized_x: 0.5,
+                normalized_y: 0.75,
+            },
+            frame_origins: vec![],
+            cell_spacing: 0,
+            scale: 1,
+            shared_bounds: None,
         };
 
         let json_result = serde_json::to_string_pretty(&animation_json);
@@ -144,6 +237,10 @@ mod tests {
         assert_eq!(parsed["frameRect"]["height"], 168);
         assert_eq!(parsed["imageWidth"], 752);
         assert_eq!(parsed["imageHeight"], 1344);
+        assert_eq!(parsed["origin"]["x"], 98);
+        assert_eq!(parsed["origin"]["y"], 127);
+        assert_eq!(parsed["origin"]["normalizedX"], 0.5);
+        assert_eq!(parsed["origin"]["normalizedY"], 0.75);
 
         // Ensure no extra fields at root level
         let expected_keys = [
@@ -153,6 +250,8 @@ mod tests {
             "frameRect",
             "imageWidth",
             "imageHeight",
+            "origin",
+            "frameOrigins",
         ];
         assert_eq!(parsed.as_object().unwrap().len(), expected_keys.len());
         for key in expected_keys {
@@ -175,6 +274,8 @@ mod tests {
         assert!(parsed["frameRect"].is_object());
         assert!(parsed["imageWidth"].is_u64());
         assert!(parsed["imageHeight"].is_u64());
+        assert!(parsed["origin"].is_object());
+        assert!(parsed["frameOrigins"].is_array());
 
         // Validate frameDelays array contains only numbers
         let delays = parsed["frameDelays"].as_array().unwrap();
@@ -182,4 +283,121 @@ mod tests {
             assert!(delay.is_u64());
         }
     }
+
+    #[test]
+    fn test_origin_from_frame_rect() {
+        let frame_rect = Rect {
+            x: 902,
+            y: 873,
+            width: 196,
+            height: 254,
+        };
+
+        let origin = Origin::from_frame_rect(frame_rect);
+        assert_eq!(origin.x, 98);
+        assert_eq!(origin.y, 127);
+        assert_eq!(origin.normalized_x, 0.5);
+        assert_eq!(origin.normalized_y, 0.5);
+    }
+
+    #[test]
+    fn test_origin_includes_cgs_offset() {
+        let frame_rect = Rect {
+            x: 900,
+            y: 900,
+            width: 200,
+            height: 200,
+        };
+
+        let origin = Origin::new(frame_rect, -20, 50);
+        assert_eq!(origin.x, 80);
+        assert_eq!(origin.y, 150);
+        assert_eq!(origin.normalized_x, 0.4);
+        assert_eq!(origin.normalized_y, 0.75);
+    }
+
+    #[test]
+    fn test_animation_json_scale() {
+        let frames = vec![cgs::CompositeFrame {
+            frame_idx: 0,
+            offset_x: 5,
+            offset_y: 0,
+            image: image::RgbaImage::new(1, 1).into(),
+            rect: Rect::default(),
+            delay: 1,
+        }];
+        let frame_rect = Rect {
+            x: 950,
+            y: 960,
+            width: 100,
+            height: 80,
+        };
+
+        let mut animation_json =
+            AnimationJson::from_frames(1, "idle".to_string(), &frames, frame_rect, 400, 160);
+        animation_json.scale(2);
+
+        assert_eq!(animation_json.scale, 2);
+        assert_eq!(animation_json.frame_rect.x, 1900);
+        assert_eq!(animation_json.frame_rect.width, 200);
+        assert_eq!(animation_json.frame_rect.height, 160);
+        assert_eq!(animation_json.origin.x, 100);
+        assert_eq!(animation_json.origin.y, 80);
+        assert_eq!(animation_json.origin.normalized_x, 0.5);
+        assert_eq!(animation_json.frame_origins[0].x, 110);
+
+        let parsed = serde_json::to_value(&animation_json).unwrap();
+        assert_eq!(parsed["scale"], 2);
+    }
+
+    #[test]
+    fn test_animation_json_shared_bounds() {
+        let frame_rect = Rect {
+            x: 950,
+            y: 960,
+            width: 100,
+            height: 80,
+        };
+        let mut animation_json =
+            AnimationJson::from_frames(1, "idle".to_string(), &[], frame_rect, 100, 80);
+        let parsed = serde_json::to_value(&animation_json).unwrap();
+        assert!(parsed.get("sharedBounds").is_none());
+
+        animation_json.shared_bounds = Some(frame_rect);
+        animation_json.scale(2);
+        assert_eq!(animation_json.shared_bounds, Some(frame_rect.scaled(2)));
+
+        let parsed = serde_json::to_value(&animation_json).unwrap();
+        assert_eq!(parsed["sharedBounds"]["x"], 1900);
+        assert_eq!(parsed["sharedBounds"]["width"], 200);
+        assert_eq!(parsed["sharedBounds"]["height"], 160);
+    }
+
+    #[test]
+    fn test_animation_json_frame_origins() {
+        let frame = |offset_x, offset_y| cgs::CompositeFrame {
+            frame_idx: 0,
+            offset_x,
+            offset_y,
+            image: image::RgbaImage::new(1, 1).into(),
+            rect: Rect::default(),
+            delay: 1,
+        };
+        let frames = vec![frame(0, 0), frame(10, -10)];
+        let frame_rect = Rect {
+            x: 950,
+            y: 950,
+            width: 100,
+            height: 100,
+        };
+
+        let animation_json =
+            AnimationJson::from_frames(1, "idle".to_string(), &frames, frame_rect, 200, 100);
+
+        assert_eq!(animation_json.origin, Origin::from_frame_rect(frame_rect));
+        assert_eq!(animation_json.frame_origins.len(), 2);
+        assert_eq!(animation_json.frame_origins[0], animation_json.origin);
+        assert_eq!(animation_json.frame_origins[1].x, 60);
+        assert_eq!(animation_json.frame_origins[1].y, 40);
+    }
 }
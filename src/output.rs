@@ -0,0 +1,708 @@
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::{FfbeError, Result, character_db::CharacterInfo};
+
+/// Template reproducing the historical file names: `{uid}-{anim}.png`, `{uid}-{anim}.json`,
+/// `{uid}-{anim}-anim.png`, `{uid}-{anim}-anim.gif` and `{uid}-{anim}-layers`.
+pub const DEFAULT_TEMPLATE: &str = "{uid}-{anim}{suffix}.{ext}";
+
+/// Substituted for `{name}` and `{rarity}` when the character database has no value.
+const UNKNOWN: &str = "unknown";
+
+/// One of the files (or, for layers and slices, folders) written for an animation, or for
+/// the whole unit by the atlas commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    Spritesheet,
+    Json,
+    Apng,
+    Gif,
+    Layers,
+    /// The atlas slices written by `slice`.
+    Slices,
+    /// The atlas coverage image written by `atlas-map`.
+    AtlasMap,
+}
+
+impl OutputKind {
+    /// Value of the `{format}` placeholder.
+    pub fn format(self) -> &'static str {
+        match self {
+            OutputKind::Spritesheet => "sheet",
+            OutputKind::Json => "json",
+            OutputKind::Apng => "apng",
+            OutputKind::Gif => "gif",
+            OutputKind::Layers => "layers",
+            OutputKind::Slices => "slices",
+            OutputKind::AtlasMap => "atlas-map",
+        }
+    }
+
+    /// Value of the `{ext}` placeholder. Layers and slices are folders and have no
+    /// extension.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputKind::Spritesheet | OutputKind::Apng | OutputKind::AtlasMap => "png",
+            OutputKind::Json => "json",
+            OutputKind::Gif => "gif",
+            OutputKind::Layers | OutputKind::Slices => "",
+        }
+    }
+
+    /// Value of the `{suffix}` placeholder, the suffix of the historical file names.
+    fn suffix(self) -> &'static str {
+        match self {
+            OutputKind::Spritesheet
+            | OutputKind::Json
+            | OutputKind::Slices
+            | OutputKind::AtlasMap => "",
+            OutputKind::Apng | OutputKind::Gif => "-anim",
+            OutputKind::Layers => "-layers",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Uid,
+    Name,
+    Anim,
+    Format,
+    Rarity,
+    Ext,
+    Suffix,
+}
+
+impl FromStr for Placeholder {
+    type Err = FfbeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "uid" => Ok(Placeholder::Uid),
+            "name" => Ok(Placeholder::Name),
+            "anim" => Ok(Placeholder::Anim),
+            "format" => Ok(Placeholder::Format),
+            "rarity" => Ok(Placeholder::Rarity),
+            "ext" => Ok(Placeholder::Ext),
+            "suffix" => Ok(Placeholder::Suffix),
+            _ => Err(FfbeError::InvalidInput(format!(
+                "Unknown placeholder '{{{s}}}' in output template; expected one of \
+                 {{uid}}, {{name}}, {{anim}}, {{format}}, {{rarity}}, {{ext}} or {{suffix}}"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A relative output path such as `{name}/{anim}/{uid}_{anim}.{ext}`, expanded once per
+/// output file. `/` separates directories.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Default for OutputTemplate {
+    fn default() -> Self {
+        DEFAULT_TEMPLATE.parse().expect("default template is valid")
+    }
+}
+
+impl fmt::Display for OutputTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = FfbeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |message: &str| {
+            Err(FfbeError::InvalidInput(format!(
+                "Invalid output template '{s}': {message}"
+            )))
+        };
+
+        if s.starts_with('/') || s.starts_with('\\') {
+            return invalid("it must be relative to the output directory");
+        }
+        if s.split(['/', '\\']).any(|component| component == "..") {
+            return invalid("'..' is not allowed");
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = s;
+        while !rest.is_empty() {
+            match rest.find(['{', '}']) {
+                Some(start) if rest[start..].starts_with('}') => {
+                    return invalid("unmatched '}'");
+                }
+                Some(start) => {
+                    let Some(len) = rest[start..].find('}') else {
+                        return invalid("unmatched '{'");
+                    };
+                    if start > 0 {
+                        segments.push(Segment::Literal(rest[..start].to_string()));
+                    }
+                    let placeholder = rest[start + 1..start + len].parse()?;
+                    segments.push(Segment::Placeholder(placeholder));
+                    rest = &rest[start + len + 1..];
+                }
+                None => {
+                    segments.push(Segment::Literal(rest.to_string()));
+                    rest = "";
+                }
+            }
+        }
+
+        if !segments.contains(&Segment::Placeholder(Placeholder::Anim)) {
+            return invalid("it must contain {anim} so animations do not overwrite each other");
+        }
+        let Some(last) = segments.last() else {
+            return invalid("it is empty");
+        };
+        if matches!(last, Segment::Literal(text) if text.ends_with(['/', '\\'])) {
+            return invalid("it must end with a file name");
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            segments,
+        })
+    }
+}
+
+impl OutputTemplate {
+    /// Whether expanding the template needs the character database.
+    pub fn uses_character_info(&self) -> bool {
+        self.segments.iter().any(|segment| {
+            matches!(
+                segment,
+                Segment::Placeholder(Placeholder::Name | Placeholder::Rarity)
+            )
+        })
+    }
+
+    /// Expands the template for one output. An empty `{ext}` also drops the `.` before it.
+    pub fn expand(
+        &self,
+        uid: u32,
+        character: Option<&CharacterInfo>,
+        anim: &str,
+        kind: OutputKind,
+    ) -> PathBuf {
+        let name = character.map(|info| info.name.as_str());
+        let rarity = character.and_then(|info| info.rarity.as_deref());
+
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => path.push_str(text),
+                Segment::Placeholder(placeholder) => {
+                    let value = match placeholder {
+                        Placeholder::Uid => uid.to_string(),
+                        Placeholder::Name => sanitize(name.unwrap_or_default()),
+                        Placeholder::Anim => sanitize(anim),
+                        Placeholder::Format => kind.format().to_string(),
+                        Placeholder::Rarity => sanitize(rarity.unwrap_or_default()),
+                        Placeholder::Ext => kind.extension().to_string(),
+                        Placeholder::Suffix => kind.suffix().to_string(),
+                    };
+                    if *placeholder == Placeholder::Ext && value.is_empty() && path.ends_with('.') {
+                        path.pop();
+                    }
+                    path.push_str(&value);
+                }
+            }
+        }
+
+        path.split(['/', '\\']).collect()
+    }
+}
+
+/// What to do when an output file already exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace it
+    #[default]
+    Always,
+    /// Keep it and skip the output
+    Never,
+    /// Replace it only when one of the animation's inputs was modified after it
+    IfNewer,
+    /// Fail the animation
+    Error,
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(OverwritePolicy::Always),
+            "never" => Ok(OverwritePolicy::Never),
+            "if-newer" => Ok(OverwritePolicy::IfNewer),
+            "error" => Ok(OverwritePolicy::Error),
+            _ => Err(format!(
+                "Invalid overwrite policy '{s}': expected 'always', 'never', 'if-newer' or 'error'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OverwritePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverwritePolicy::Always => write!(f, "always"),
+            OverwritePolicy::Never => write!(f, "never"),
+            OverwritePolicy::IfNewer => write!(f, "if-newer"),
+            OverwritePolicy::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What writing an output would do under the overwrite policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputAction {
+    /// The output does not exist yet.
+    #[serde(rename = "write")]
+    Create,
+    Overwrite,
+    /// The existing output is kept and nothing is written.
+    Keep,
+    /// The output exists and the policy is [`OverwritePolicy::Error`].
+    Conflict,
+    /// The animation is unchanged since the last render (see [`crate::manifest`]), so the
+    /// output is not rendered again.
+    Skip,
+}
+
+impl fmt::Display for OutputAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputAction::Create => write!(f, "write"),
+            OutputAction::Overwrite => write!(f, "overwrite"),
+            OutputAction::Keep => write!(f, "keep"),
+            OutputAction::Conflict => write!(f, "conflict"),
+            OutputAction::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Resolves the output paths of a unit's animations inside an output directory and writes
+/// the outputs under an overwrite policy.
+#[derive(Clone, Debug)]
+pub struct OutputPaths {
+    pub output_dir: PathBuf,
+    pub template: OutputTemplate,
+    pub uid: u32,
+    /// Database entry for `{name}` and `{rarity}`.
+    pub character: Option<CharacterInfo>,
+    pub overwrite: OverwritePolicy,
+    /// Directory of the unit's input files, compared against by [`OverwritePolicy::IfNewer`].
+    pub input_dir: PathBuf,
+}
+
+impl OutputPaths {
+    pub fn new(output_dir: impl AsRef<Path>, template: OutputTemplate, uid: u32) -> Self {
+        Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            template,
+            uid,
+            character: None,
+            overwrite: OverwritePolicy::default(),
+            input_dir: PathBuf::from("."),
+        }
+    }
+
+    pub fn path(&self, anim: &str, kind: OutputKind) -> PathBuf {
+        self.output_dir.join(
+            self.template
+                .expand(self.uid, self.character.as_ref(), anim, kind),
+        )
+    }
+
+    /// Fails when two of `kinds` expand to the same path for `anim`, which happens with
+    /// templates that use neither `{format}` nor `{suffix}`: one output would silently
+    /// replace the other.
+    pub fn check_distinct(&self, anim: &str, kinds: &[OutputKind]) -> Result<()> {
+        for (index, &kind) in kinds.iter().enumerate() {
+            let path = self.path(anim, kind);
+            if let Some(&other) = kinds[..index]
+                .iter()
+                .find(|&&other| self.path(anim, other) == path)
+            {
+                return Err(FfbeError::InvalidInput(format!(
+                    "The {} and {} outputs of {anim} would both be written to {}; add {{format}} or {{suffix}} to the output template",
+                    other.format(),
+                    kind.format(),
+                    path.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// What writing the output would do, given whether it exists and the overwrite policy.
+    pub fn action(&self, anim: &str, kind: OutputKind) -> OutputAction {
+        let Ok(metadata) = fs::metadata(self.path(anim, kind)) else {
+            return OutputAction::Create;
+        };
+
+        match self.overwrite {
+            OverwritePolicy::Always => OutputAction::Overwrite,
+            OverwritePolicy::Never => OutputAction::Keep,
+            OverwritePolicy::Error => OutputAction::Conflict,
+            OverwritePolicy::IfNewer => match (metadata.modified(), self.inputs_modified(anim)) {
+                (Ok(output), Some(inputs)) if output >= inputs => OutputAction::Keep,
+                _ => OutputAction::Overwrite,
+            },
+        }
+    }
+
+    /// Writes one output unless the overwrite policy keeps the existing one. `write`
+    /// receives a temporary path next to the final one, which is renamed into place once
+    /// `write` succeeds so that an interrupted run never leaves a partial output. Missing
+    /// directories are created.
+    pub fn write(
+        &self,
+        anim: &str,
+        kind: OutputKind,
+        write: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<WriteOutcome> {
+        let path = self.path(anim, kind);
+        match self.action(anim, kind) {
+            OutputAction::Keep | OutputAction::Skip => return Ok(WriteOutcome::Kept(path)),
+            OutputAction::Conflict => return Err(conflict_error(&path)),
+            OutputAction::Create | OutputAction::Overwrite => {}
+        }
+
+        write_atomic(&path, write)?;
+        Ok(WriteOutcome::Written(path))
+    }
+
+    /// Latest modification time of the unit's cgg file, atlas and the animation's cgs file.
+    fn inputs_modified(&self, anim: &str) -> Option<SystemTime> {
+        let uid = self.uid;
+        [
+            format!("unit_cgg_{uid}.csv"),
+            format!("unit_anime_{uid}.png"),
+            format!("unit_{anim}_cgs_{uid}.csv"),
+        ]
+        .iter()
+        .filter_map(|file| {
+            fs::metadata(self.input_dir.join(file))
+                .ok()?
+                .modified()
+                .ok()
+        })
+        .max()
+    }
+}
+
+/// Result of [`OutputPaths::write`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written(PathBuf),
+    /// The overwrite policy kept the existing output.
+    Kept(PathBuf),
+}
+
+/// The error for an output that exists while the overwrite policy is `error`.
+pub fn conflict_error(path: &Path) -> FfbeError {
+    FfbeError::InvalidInput(format!(
+        "{} already exists and the overwrite policy is 'error'",
+        path.display()
+    ))
+}
+
+/// Writes `path` (a file or a directory) through a temporary sibling that `write` fills
+/// and that then replaces `path`. The temporary keeps the extension so that encoders
+/// choosing the format from the file name still work. It is removed if `write` fails.
+pub fn write_atomic(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_name = match path.extension() {
+        Some(ext) => format!(
+            ".{stem}.partial-{}.{}",
+            std::process::id(),
+            ext.to_string_lossy()
+        ),
+        None => format!(".{stem}.partial-{}", std::process::id()),
+    };
+    let temp_path = parent.join(temp_name);
+
+    let remove = |path: &Path| {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    };
+
+    if let Err(err) = write(&temp_path) {
+        let _ = remove(&temp_path);
+        return Err(err);
+    }
+
+    // Renaming cannot replace a directory, so a previous directory output goes first
+    if temp_path.is_dir() && path.is_dir() {
+        fs::remove_dir_all(path)?;
+    }
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = remove(&temp_path);
+    })?;
+    Ok(())
+}
+
+/// Makes a placeholder value safe to use as (part of) a single path component.
+fn sanitize(value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() || value.chars().all(|c| c == '.') {
+        return UNKNOWN.to_string();
+    }
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character(name: &str, rarity: &str) -> CharacterInfo {
+        CharacterInfo {
+            r#type: "story".to_string(),
+            name: name.to_string(),
+            rarity: Some(rarity.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_default_template_matches_legacy_names() {
+        let paths = OutputPaths::new("out", OutputTemplate::default(), 123);
+
+        assert_eq!(
+            paths.path("atk", OutputKind::Spritesheet),
+            Path::new("out/123-atk.png")
+        );
+        assert_eq!(
+            paths.path("atk", OutputKind::Json),
+            Path::new("out/123-atk.json")
+        );
+        assert_eq!(
+            paths.path("atk", OutputKind::Apng),
+            Path::new("out/123-atk-anim.png")
+        );
+        assert_eq!(
+            paths.path("atk", OutputKind::Gif),
+            Path::new("out/123-atk-anim.gif")
+        );
+        assert_eq!(
+            paths.path("atk", OutputKind::Layers),
+            Path::new("out/123-atk-layers")
+        );
+        assert_eq!(
+            paths.path("atlas-map", OutputKind::AtlasMap),
+            Path::new("out/123-atlas-map.png")
+        );
+        assert_eq!(
+            paths.path("slices", OutputKind::Slices),
+            Path::new("out/123-slices")
+        );
+    }
+
+    #[test]
+    fn test_expand_character_placeholders() {
+        let template: OutputTemplate = "{name}/{rarity}/{anim}/{uid}_{format}.{ext}"
+            .parse()
+            .unwrap();
+        assert!(template.uses_character_info());
+
+        let info = character("Cloud/Zack", "NV (SLB)");
+        assert_eq!(
+            template.expand(1, Some(&info), "limit_atk", OutputKind::Apng),
+            Path::new("Cloud_Zack/NV (SLB)/limit_atk/1_apng.png")
+        );
+        assert_eq!(
+            template.expand(1, None, "idle", OutputKind::Layers),
+            Path::new("unknown/unknown/idle/1_layers")
+        );
+        assert!(!OutputTemplate::default().uses_character_info());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_templates() {
+        for template in [
+            "{uid}-{animation}.{ext}",
+            "{uid}-{anim.{ext}",
+            "{uid}}-{anim}.{ext}",
+            "/tmp/{anim}.{ext}",
+            "../{anim}.{ext}",
+            "{uid}.{ext}",
+            "{anim}/",
+        ] {
+            assert!(
+                template.parse::<OutputTemplate>().is_err(),
+                "{template} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_distinct_rejects_colliding_outputs() {
+        let kinds = [
+            OutputKind::Spritesheet,
+            OutputKind::Apng,
+            OutputKind::Json,
+            OutputKind::Layers,
+        ];
+
+        let paths = OutputPaths::new("out", "{anim}/{uid}_{anim}.{ext}".parse().unwrap(), 1);
+        let err = paths.check_distinct("atk", &kinds).unwrap_err();
+        assert!(err.to_string().contains("sheet and apng"), "{err}");
+        assert!(
+            paths
+                .check_distinct("atk", &[OutputKind::Spritesheet, OutputKind::Gif])
+                .is_ok()
+        );
+
+        for template in [DEFAULT_TEMPLATE, "{anim}/{uid}_{format}.{ext}"] {
+            let paths = OutputPaths::new("out", template.parse().unwrap(), 1);
+            assert!(paths.check_distinct("atk", &kinds).is_ok(), "{template}");
+        }
+    }
+
+    #[test]
+    fn test_write_creates_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths = OutputPaths::new(
+            temp_dir.path(),
+            "{uid}/{anim}/{format}.{ext}".parse().unwrap(),
+            7,
+        );
+
+        let outcome = paths
+            .write("atk", OutputKind::Gif, |path| {
+                assert_eq!(path.extension().unwrap(), "gif");
+                Ok(fs::write(path, "gif")?)
+            })
+            .unwrap();
+        let path = temp_dir.path().join("7/atk/gif.gif");
+        assert_eq!(outcome, WriteOutcome::Written(path.clone()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "gif");
+        assert_eq!(
+            fs::read_dir(temp_dir.path().join("7/atk")).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_write_failure_keeps_previous_output() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths = OutputPaths::new(temp_dir.path(), OutputTemplate::default(), 7);
+        let path = paths.path("atk", OutputKind::Spritesheet);
+        fs::write(&path, "old").unwrap();
+
+        let result = paths.write("atk", OutputKind::Spritesheet, |path| {
+            fs::write(path, "half")?;
+            Err(FfbeError::InvalidInput("interrupted".to_string()))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_replaces_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths = OutputPaths::new(temp_dir.path(), OutputTemplate::default(), 7);
+        let layers = |file: &'static str| {
+            move |dir: &Path| {
+                fs::create_dir_all(dir)?;
+                Ok(fs::write(dir.join(file), "")?)
+            }
+        };
+
+        paths
+            .write("atk", OutputKind::Layers, layers("old.png"))
+            .unwrap();
+        paths
+            .write("atk", OutputKind::Layers, layers("new.png"))
+            .unwrap();
+
+        let dir = temp_dir.path().join("7-atk-layers");
+        assert!(dir.join("new.png").exists());
+        assert!(!dir.join("old.png").exists());
+    }
+
+    #[test]
+    fn test_overwrite_policies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let input_dir = temp_dir.path().join("input");
+        fs::create_dir(&input_dir).unwrap();
+        let mut paths = OutputPaths::new(temp_dir.path(), OutputTemplate::default(), 7);
+        paths.input_dir = input_dir.clone();
+        let write = |paths: &OutputPaths| {
+            paths.write("atk", OutputKind::Json, |path| Ok(fs::write(path, "new")?))
+        };
+
+        assert_eq!(paths.action("atk", OutputKind::Json), OutputAction::Create);
+        fs::write(paths.path("atk", OutputKind::Json), "old").unwrap();
+
+        paths.overwrite = OverwritePolicy::Never;
+        assert!(matches!(write(&paths).unwrap(), WriteOutcome::Kept(_)));
+        paths.overwrite = OverwritePolicy::Error;
+        assert!(write(&paths).is_err());
+        assert_eq!(
+            paths.action("atk", OutputKind::Json),
+            OutputAction::Conflict
+        );
+
+        // The output is newer than the (missing, then older) inputs
+        paths.overwrite = OverwritePolicy::IfNewer;
+        assert_eq!(
+            paths.action("atk", OutputKind::Json),
+            OutputAction::Overwrite
+        );
+        let cgs = fs::File::create(input_dir.join("unit_atk_cgs_7.csv")).unwrap();
+        cgs.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(paths.action("atk", OutputKind::Json), OutputAction::Keep);
+        cgs.set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            paths.action("atk", OutputKind::Json),
+            OutputAction::Overwrite
+        );
+
+        paths.overwrite = OverwritePolicy::Always;
+        assert!(matches!(write(&paths).unwrap(), WriteOutcome::Written(_)));
+        assert_eq!(
+            fs::read_to_string(paths.path("atk", OutputKind::Json)).unwrap(),
+            "new"
+        );
+    }
+}
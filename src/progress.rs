@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A step of rendering a unit, passed to a [`ProgressHook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Progress<'a> {
+    /// A unit starts rendering `animations` animations.
+    UnitStarted {
+        unit_id: u32,
+        animations: usize,
+    },
+    AnimationStarted {
+        name: &'a str,
+    },
+    /// `done` of the `total` distinct frames of the animation are composited.
+    FrameComposited {
+        name: &'a str,
+        done: usize,
+        total: usize,
+    },
+    /// An animation was written or failed.
+    AnimationFinished {
+        name: &'a str,
+    },
+    UnitFinished {
+        unit_id: u32,
+    },
+}
+
+/// Receives [`Progress`] while a unit renders. Animations and their frames are rendered in
+/// parallel, so hooks are called from several threads and the events of different
+/// animations interleave.
+pub trait ProgressHook: Sync {
+    fn progress(&self, progress: Progress);
+}
+
+impl<F: Fn(Progress) + Sync> ProgressHook for F {
+    fn progress(&self, progress: Progress) {
+        self(progress)
+    }
+}
+
+/// A hook that ignores all progress.
+pub fn no_progress(_: Progress) {}
+
+/// Follows the progress of a unit to estimate how much of it is done and how long the rest
+/// will take.
+#[derive(Debug)]
+pub struct ProgressTracker {
+    start: Instant,
+    animations: usize,
+    animations_done: usize,
+    /// Composited and total frames of the animations in progress.
+    frames: HashMap<String, (usize, usize)>,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            animations: 0,
+            animations_done: 0,
+            frames: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, progress: Progress) {
+        match progress {
+            Progress::UnitStarted { animations, .. } => {
+                *self = Self {
+                    animations,
+                    ..Self::new()
+                }
+            }
+            Progress::AnimationStarted { name } => {
+                self.frames.insert(name.to_string(), (0, 0));
+            }
+            Progress::FrameComposited { name, done, total } => {
+                // Frames finish out of order, so never move backwards
+                let frames = self.frames.entry(name.to_string()).or_default();
+                *frames = (frames.0.max(done), total);
+            }
+            Progress::AnimationFinished { name } => {
+                self.animations_done += 1;
+                self.frames.remove(name);
+            }
+            Progress::UnitFinished { .. } => self.animations_done = self.animations,
+        }
+    }
+
+    pub fn animations(&self) -> (usize, usize) {
+        (self.animations_done, self.animations)
+    }
+
+    /// Share of the unit done, counting the composited frames of the animations in progress.
+    pub fn fraction(&self) -> f64 {
+        if self.animations == 0 {
+            return 0.0;
+        }
+        let in_progress: f64 = self
+            .frames
+            .values()
+            .filter(|(_, total)| *total > 0)
+            .map(|(done, total)| *done as f64 / *total as f64)
+            .sum();
+        ((self.animations_done as f64 + in_progress) / self.animations as f64).min(1.0)
+    }
+
+    /// Estimated time left, assuming the rest renders at the pace so far. `None` until
+    /// anything is done.
+    pub fn eta(&self) -> Option<Duration> {
+        self.remaining(self.start.elapsed())
+    }
+
+    fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        let fraction = self.fraction();
+        (fraction > 0.0).then(|| elapsed.mul_f64((1.0 - fraction) / fraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_fraction_and_eta() {
+        let mut tracker = ProgressTracker::new();
+        assert_eq!(tracker.fraction(), 0.0);
+
+        tracker.update(Progress::UnitStarted {
+            unit_id: 1,
+            animations: 4,
+        });
+        assert_eq!(tracker.remaining(Duration::from_secs(1)), None);
+
+        tracker.update(Progress::AnimationStarted { name: "atk" });
+        tracker.update(Progress::AnimationStarted { name: "idle" });
+        let frames = |name, done| Progress::FrameComposited {
+            name,
+            done,
+            total: 4,
+        };
+        tracker.update(frames("atk", 2));
+        tracker.update(frames("atk", 1));
+        assert_eq!(tracker.fraction(), 0.125);
+        tracker.update(frames("idle", 2));
+        assert_eq!(tracker.fraction(), 0.25);
+
+        tracker.update(Progress::AnimationFinished { name: "atk" });
+        assert_eq!(tracker.animations(), (1, 4));
+        assert_eq!(tracker.fraction(), 0.375);
+        tracker.update(Progress::AnimationFinished { name: "idle" });
+        assert_eq!(tracker.fraction(), 0.5);
+        assert_eq!(
+            tracker.remaining(Duration::from_secs(2)),
+            Some(Duration::from_secs(2))
+        );
+
+        tracker.update(Progress::UnitFinished { unit_id: 1 });
+        assert_eq!(tracker.fraction(), 1.0);
+        assert_eq!(
+            tracker.remaining(Duration::from_secs(2)),
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,1746 @@
+use image::{DynamicImage, RgbaImage, imageops};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::{
+    FfbeError, Result, Unit, cgg,
+    cgs::{self, process_frames_with_progress},
+    discovery,
+    imageops::{Rect, Scaler},
+    layers,
+    layout::{Background, LayoutOptions},
+    manifest::{InputHasher, Manifest},
+    metadata,
+    output::{self, OutputAction, OutputKind, OutputPaths, OutputTemplate, WriteOutcome},
+    progress::{Progress, ProgressHook},
+    report::{AnimationReport, AnimationStatus, ErrorReport, OutputReport, Reporter},
+};
+
+/// Everything that decides how the animations of a unit are rendered, apart from where
+/// the outputs go, which [`OutputPaths`] decides.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Directory holding the unit's cgg, cgs and atlas files.
+    pub input_dir: String,
+    /// Spritesheet columns; 0 puts every frame in one row.
+    pub columns: usize,
+    /// Keep frames without any content.
+    pub include_empty: bool,
+    /// Collapse consecutive identical frames into one in animated outputs.
+    pub collapse: bool,
+    /// Crop every animation to the bounds of all the animations rendered together.
+    pub shared_bounds: bool,
+    pub save_layers: bool,
+    pub debug_overlay: bool,
+    pub save_json: bool,
+    /// Takes precedence over `save_apng` when both are set.
+    pub save_gif: bool,
+    pub save_apng: bool,
+    pub scale: u32,
+    pub scaler: Scaler,
+    pub layout: LayoutOptions,
+    /// Re-render animations even when their inputs and options are unchanged.
+    pub force: bool,
+    /// Only list the outputs in each animation's report, without writing anything.
+    pub dry_run: bool,
+    /// Animations rendered at once, and threads rendering them; 0 uses one per CPU core.
+    pub jobs: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            input_dir: ".".to_string(),
+            columns: 0,
+            include_empty: false,
+            collapse: false,
+            shared_bounds: false,
+            save_layers: false,
+            debug_overlay: false,
+            save_json: false,
+            save_gif: false,
+            save_apng: false,
+            scale: 1,
+            scaler: Scaler::default(),
+            layout: LayoutOptions::default(),
+            force: false,
+            dry_run: false,
+            jobs: 0,
+        }
+    }
+}
+
+/// The animated image written next to the spritesheet, if any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimFileType {
+    Gif,
+    Apng,
+    None,
+}
+
+impl From<&str> for AnimFileType {
+    fn from(value: &str) -> Self {
+        match value {
+            "apng" => AnimFileType::Apng,
+            "gif" => AnimFileType::Gif,
+            _ => AnimFileType::None,
+        }
+    }
+}
+
+/// A loaded unit and the options its animations are rendered with.
+pub struct RenderContext<'a> {
+    pub options: &'a RenderOptions,
+    pub outputs: OutputPaths,
+    pub unit: Unit,
+    pub src_img: DynamicImage,
+    pub part_cache: cgs::PartCache,
+    anim_file_type: AnimFileType,
+    pub progress: &'a dyn ProgressHook,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new(
+        options: &'a RenderOptions,
+        outputs: OutputPaths,
+        unit: Unit,
+        src_img: DynamicImage,
+        progress: &'a dyn ProgressHook,
+    ) -> Self {
+        Self {
+            options,
+            outputs,
+            unit,
+            src_img,
+            part_cache: cgs::PartCache::new(),
+            anim_file_type: determine_animation_file_type(options),
+            progress,
+        }
+    }
+}
+
+/// Renders every animation whose inputs changed, reporting each one. Failed animations do
+/// not stop the others, but make the whole call fail at the end. For a dry run the outputs
+/// each animation would write are listed in its report instead.
+pub fn process_animations(
+    ctx: &RenderContext,
+    animations: &[String],
+    manifest: &mut Manifest,
+    reporter: &mut Reporter,
+) -> Result<()> {
+    let options = ctx.options;
+    let uid = ctx.outputs.uid;
+
+    let kinds = output_kinds(options, ctx.anim_file_type);
+    for anim_name in animations {
+        ctx.outputs.check_distinct(anim_name, &kinds)?;
+    }
+
+    let unit_hasher = unit_input_hasher(options, &ctx.outputs.template, uid)?;
+    let mut pending_animations = Vec::new();
+    for anim_name in animations {
+        let hash = animation_input_hash(&unit_hasher, options, uid, anim_name)?;
+        if is_up_to_date(
+            options,
+            &ctx.outputs,
+            manifest,
+            anim_name,
+            &hash,
+            ctx.anim_file_type,
+        ) {
+            let mut report = AnimationReport::new(uid, anim_name, AnimationStatus::Skipped);
+            if options.dry_run {
+                let plan = skipped_outputs(options, &ctx.outputs, anim_name, ctx.anim_file_type);
+                report.outputs = plan
+                    .into_iter()
+                    .map(|output| output.report(false))
+                    .collect();
+            }
+            reporter.animation(report)?;
+        } else {
+            manifest.remove(&Manifest::key(uid, anim_name));
+            pending_animations.push((anim_name, hash));
+        }
+    }
+    let skipped_count = reporter.unit.count(AnimationStatus::Skipped);
+    if skipped_count > 0 {
+        log::info!("Skipping {skipped_count} unchanged animations (use --force to re-render)");
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs)
+        .build()
+        .map_err(|err| {
+            FfbeError::InvalidInput(format!("Cannot start {} jobs: {err}", options.jobs))
+        })?;
+    let shared_rect = if pending_animations.is_empty() {
+        None
+    } else {
+        pool.install(|| calculate_shared_frame_rect(ctx, animations))
+    };
+    let status = if options.dry_run {
+        AnimationStatus::Planned
+    } else {
+        AnimationStatus::Rendered
+    };
+
+    ctx.progress.progress(Progress::UnitStarted {
+        unit_id: uid,
+        animations: pending_animations.len(),
+    });
+    // Each worker renders one animation at a time, so at most `jobs` animations keep their
+    // frames in memory. Their frames are composited on the same pool.
+    let next_animation = AtomicUsize::new(0);
+    let finished = Mutex::new(FinishedAnimations {
+        manifest,
+        reporter,
+        processed_count: 0,
+        failed_animations: Vec::new(),
+        report_error: None,
+    });
+    std::thread::scope(|scope| {
+        for _ in 0..pool.current_num_threads().min(pending_animations.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next_animation.fetch_add(1, Ordering::Relaxed);
+                    let Some((anim_name, hash)) = pending_animations.get(index) else {
+                        break;
+                    };
+                    let (report, result) =
+                        pool.install(|| render_reported(ctx, anim_name, shared_rect, status));
+                    finished
+                        .lock()
+                        .unwrap()
+                        .record(uid, index, anim_name, hash, report, result);
+                    ctx.progress
+                        .progress(Progress::AnimationFinished { name: anim_name });
+                }
+            });
+        }
+    });
+    ctx.progress
+        .progress(Progress::UnitFinished { unit_id: uid });
+
+    let FinishedAnimations {
+        processed_count,
+        mut failed_animations,
+        report_error,
+        ..
+    } = finished.into_inner().unwrap();
+    if let Some(err) = report_error {
+        return Err(err);
+    }
+    failed_animations.sort();
+    let failed_animations: Vec<&str> = failed_animations
+        .into_iter()
+        .map(|(_, anim_name)| anim_name)
+        .collect();
+
+    log::info!("\nProcessing complete:");
+    if options.dry_run {
+        log::info!("✓ Planned: {processed_count} animations (dry run, nothing was written)");
+    } else {
+        log::info!("✓ Successfully processed: {} animations", processed_count);
+    }
+    if skipped_count > 0 {
+        log::info!("↷ Skipped unchanged: {} animations", skipped_count);
+    }
+
+    if !failed_animations.is_empty() {
+        log::info!(
+            "✗ Failed to process: {} animations ({})",
+            failed_animations.len(),
+            failed_animations.join(", ")
+        );
+        return Err(FfbeError::InvalidInput(format!(
+            "{} of {} animations of unit {uid} failed",
+            failed_animations.len(),
+            animations.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Results of the animations rendered in parallel by `process_animations`.
+struct FinishedAnimations<'a> {
+    manifest: &'a mut Manifest,
+    reporter: &'a mut Reporter,
+    processed_count: usize,
+    /// Failed animations with their position in the rendering order.
+    failed_animations: Vec<(usize, &'a str)>,
+    /// The first error writing the progress stream.
+    report_error: Option<FfbeError>,
+}
+
+impl<'a> FinishedAnimations<'a> {
+    fn record(
+        &mut self,
+        uid: u32,
+        index: usize,
+        anim_name: &'a str,
+        hash: &str,
+        report: AnimationReport,
+        result: Result<()>,
+    ) {
+        match result {
+            Ok(()) => {
+                self.processed_count += 1;
+                if report.status == AnimationStatus::Rendered {
+                    self.manifest
+                        .record(Manifest::key(uid, anim_name), hash.to_string());
+                }
+            }
+            Err(_) => self.failed_animations.push((index, anim_name)),
+        }
+        if let Err(err) = self.reporter.animation(report) {
+            self.report_error.get_or_insert(err);
+        }
+    }
+}
+
+/// Renders one animation, logging and timing it, and returns its report.
+fn render_reported(
+    ctx: &RenderContext,
+    anim_name: &str,
+    shared_rect: Option<Rect>,
+    status: AnimationStatus,
+) -> (AnimationReport, Result<()>) {
+    log::info!("Processing animation: {}", anim_name);
+    ctx.progress
+        .progress(Progress::AnimationStarted { name: anim_name });
+    let start = Instant::now();
+    let mut report = AnimationReport::new(ctx.outputs.uid, anim_name, status);
+
+    let result = render_animation(ctx, anim_name, shared_rect, &mut report)
+        .map_err(|err| err.in_animation(ctx.outputs.uid, anim_name));
+    match &result {
+        Ok(()) if status == AnimationStatus::Rendered => {
+            log::info!("✓ Successfully processed: {}", anim_name)
+        }
+        Ok(()) => {}
+        Err(err) => {
+            log::error!("{err:#}");
+            report.status = AnimationStatus::Failed;
+            report.error = Some(ErrorReport::from(err));
+        }
+    }
+
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    (report, result)
+}
+
+/// Renders one animation and writes its outputs, or only lists them for a dry run. The
+/// frame count and outputs are recorded in `report`.
+fn render_animation(
+    ctx: &RenderContext,
+    anim_name: &str,
+    shared_rect: Option<Rect>,
+    report: &mut AnimationReport,
+) -> Result<()> {
+    let options = ctx.options;
+    let outputs = &ctx.outputs;
+    let layout = options.layout;
+
+    // Reset unit bounds for each animation
+    let mut unit = ctx.unit.clone();
+    let mut composite_frames = process_animation_frames(
+        options,
+        outputs.uid,
+        &mut unit,
+        &ctx.src_img,
+        &ctx.part_cache,
+        anim_name,
+        ctx.progress,
+    )?;
+
+    // Calculate frame bounds and resize empty frames, then crop frames
+    let frame_rect = match shared_rect {
+        Some(rect) => rect,
+        None => calculate_frame_rect(&unit, &layout)?,
+    };
+    resize_empty_frames_to_bounds(&mut composite_frames, frame_rect);
+    crop_frames_to_bounds(&mut composite_frames, frame_rect);
+    report.frames = Some(composite_frames.len());
+
+    let cell_rect = frame_rect.scaled(options.scale);
+    let mut plan = plan_outputs(
+        options,
+        outputs,
+        anim_name,
+        composite_frames.len(),
+        cell_rect,
+        ctx.anim_file_type,
+    );
+    if options.dry_run {
+        report.outputs = plan
+            .into_iter()
+            .map(|output| output.report(false))
+            .collect();
+        return Ok(());
+    }
+    // Fail before writing anything rather than leaving some of the outputs behind.
+    if let Some(conflict) = plan
+        .iter()
+        .find(|output| output.action == OutputAction::Conflict)
+    {
+        return Err(output::conflict_error(&conflict.path));
+    }
+
+    if options.save_layers {
+        report_write(outputs.write(anim_name, OutputKind::Layers, |layers_dir| {
+            save_frame_layers(
+                options,
+                &unit,
+                &ctx.src_img,
+                &ctx.part_cache,
+                layers_dir,
+                &composite_frames,
+                frame_rect,
+            )
+        })?);
+        record_output(report, &mut plan, OutputKind::Layers);
+    }
+    if options.debug_overlay {
+        draw_debug_overlay(&mut composite_frames, &unit, frame_rect)?;
+    }
+    upscale_frames(&mut composite_frames, options.scale, options.scaler)?;
+
+    // Generate outputs
+    save_animated_files(
+        options,
+        outputs,
+        anim_name,
+        &composite_frames,
+        cell_rect,
+        ctx.anim_file_type,
+    )?;
+    record_output(report, &mut plan, OutputKind::Apng);
+    record_output(report, &mut plan, OutputKind::Gif);
+    let spritesheet = create_spritesheet(&composite_frames, cell_rect, options.columns, &layout);
+    save_spritesheet(outputs, anim_name, &spritesheet)?;
+    record_output(report, &mut plan, OutputKind::Spritesheet);
+
+    if options.save_json {
+        save_json_output(
+            options,
+            outputs,
+            anim_name,
+            &composite_frames,
+            frame_rect,
+            shared_rect,
+            &spritesheet,
+        )?;
+        record_output(report, &mut plan, OutputKind::Json);
+    }
+
+    Ok(())
+}
+
+/// Moves the planned output of `kind` into the report once it is on disk, so that a failed
+/// animation still reports what it wrote.
+fn record_output(report: &mut AnimationReport, plan: &mut Vec<PlannedOutput>, kind: OutputKind) {
+    if let Some(index) = plan.iter().position(|output| output.kind == kind) {
+        report.outputs.push(plan.remove(index).report(true));
+    }
+}
+
+/// An output a dry run reports instead of writing.
+pub struct PlannedOutput {
+    pub path: PathBuf,
+    pub kind: OutputKind,
+    pub action: OutputAction,
+    /// Image dimensions; `None` for JSON.
+    pub size: Option<(u32, u32)>,
+}
+
+/// The outputs an animation of `frame_count` frames of `cell_rect` would write.
+fn plan_outputs(
+    options: &RenderOptions,
+    outputs: &OutputPaths,
+    anim_name: &str,
+    frame_count: usize,
+    cell_rect: Rect,
+    anim_file_type: AnimFileType,
+) -> Vec<PlannedOutput> {
+    let layout = options.layout;
+    let columns = match options.columns {
+        0 => frame_count.max(1),
+        columns if columns >= frame_count => frame_count.max(1),
+        columns => columns,
+    };
+    let rows = frame_count.div_ceil(columns) as u32;
+    let sheet_size = layout.sheet_size(columns as u32, rows, cell_rect);
+    let cell_size = (cell_rect.width, cell_rect.height);
+
+    output_kinds(options, anim_file_type)
+        .into_iter()
+        .map(|kind| {
+            let size = match kind {
+                OutputKind::Spritesheet => Some(sheet_size),
+                OutputKind::Json | OutputKind::Slices | OutputKind::AtlasMap => None,
+                OutputKind::Apng | OutputKind::Gif | OutputKind::Layers => Some(cell_size),
+            };
+            PlannedOutput::new(outputs, anim_name, kind, size)
+        })
+        .collect()
+}
+
+/// The outputs of an unchanged animation, which are listed with the `skip` action. Their
+/// sizes are unknown without measuring the animation.
+fn skipped_outputs(
+    options: &RenderOptions,
+    outputs: &OutputPaths,
+    anim_name: &str,
+    anim_file_type: AnimFileType,
+) -> Vec<PlannedOutput> {
+    output_kinds(options, anim_file_type)
+        .into_iter()
+        .map(|kind| PlannedOutput {
+            path: outputs.path(anim_name, kind),
+            kind,
+            action: OutputAction::Skip,
+            size: None,
+        })
+        .collect()
+}
+
+impl PlannedOutput {
+    pub fn new(
+        outputs: &OutputPaths,
+        anim_name: &str,
+        kind: OutputKind,
+        size: Option<(u32, u32)>,
+    ) -> Self {
+        Self {
+            path: outputs.path(anim_name, kind),
+            kind,
+            action: outputs.action(anim_name, kind),
+            size,
+        }
+    }
+
+    /// The report entry for this output; file sizes are only read when it was `written`.
+    pub fn report(self, written: bool) -> OutputReport {
+        let bytes = std::fs::metadata(&self.path)
+            .ok()
+            .filter(|metadata| written && metadata.is_file())
+            .map(|metadata| metadata.len());
+
+        OutputReport {
+            path: self.path,
+            format: self.kind.format(),
+            action: self.action,
+            bytes,
+            width: self.size.map(|(width, _)| width),
+            height: self.size.map(|(_, height)| height),
+        }
+    }
+}
+
+/// Every render option that changes what is written, in a stable textual form.
+fn render_options_fingerprint(options: &RenderOptions, filename: &OutputTemplate) -> String {
+    format!(
+        "columns={} empty={} collapse={} shared_bounds={} layers={} debug_overlay={} json={} \
+         gif={} apng={} scale={} scaler={} filename={} padding={} cell_align={} spacing={} \
+         background={}",
+        options.columns,
+        options.include_empty,
+        options.collapse,
+        options.shared_bounds,
+        options.save_layers,
+        options.debug_overlay,
+        options.save_json,
+        options.save_gif,
+        options.save_apng,
+        options.scale,
+        options.scaler,
+        filename,
+        options.layout.padding,
+        options.layout.cell_align,
+        options.layout.spacing,
+        options.layout.background,
+    )
+}
+
+/// Hashes the inputs shared by every animation of the unit: the render options, the cgg
+/// file, the atlas and, with `--shared-bounds`, every cgs file since each affects the bounds.
+fn unit_input_hasher(
+    options: &RenderOptions,
+    filename: &OutputTemplate,
+    uid: u32,
+) -> Result<InputHasher> {
+    let input_dir = Path::new(&options.input_dir);
+    let mut hasher = InputHasher::new();
+    hasher.add_str("options", &render_options_fingerprint(options, filename));
+    hasher.add_file(&input_dir.join(format!("unit_cgg_{uid}.csv")))?;
+    hasher.add_file(&input_dir.join(format!("unit_anime_{uid}.png")))?;
+    if options.shared_bounds {
+        for animation in discovery::discover_animations(uid, &options.input_dir)? {
+            hasher.add_file(Path::new(&animation.file_path))?;
+        }
+    }
+    Ok(hasher)
+}
+
+fn animation_input_hash(
+    unit_hasher: &InputHasher,
+    options: &RenderOptions,
+    uid: u32,
+    anim_name: &str,
+) -> Result<String> {
+    let mut hasher = unit_hasher.clone();
+    hasher
+        .add_file(&Path::new(&options.input_dir).join(format!("unit_{anim_name}_cgs_{uid}.csv")))?;
+    Ok(hasher.finish())
+}
+
+/// The outputs written for every animation with the given options.
+fn output_kinds(options: &RenderOptions, anim_file_type: AnimFileType) -> Vec<OutputKind> {
+    let mut kinds = vec![OutputKind::Spritesheet];
+    match anim_file_type {
+        AnimFileType::Apng => kinds.push(OutputKind::Apng),
+        AnimFileType::Gif => kinds.push(OutputKind::Gif),
+        AnimFileType::None => {}
+    }
+    if options.save_json {
+        kinds.push(OutputKind::Json);
+    }
+    if options.save_layers {
+        kinds.push(OutputKind::Layers);
+    }
+    kinds
+}
+
+/// Whether an animation can be skipped: `--force` is not set, it was last rendered from
+/// inputs with the same hash and every output it writes still exists.
+fn is_up_to_date(
+    options: &RenderOptions,
+    outputs: &OutputPaths,
+    manifest: &Manifest,
+    anim_name: &str,
+    hash: &str,
+    anim_file_type: AnimFileType,
+) -> bool {
+    !options.force
+        && manifest.is_current(&Manifest::key(outputs.uid, anim_name), hash)
+        && output_kinds(options, anim_file_type)
+            .into_iter()
+            .all(|kind| outputs.path(anim_name, kind).exists())
+}
+
+fn determine_animation_file_type(options: &RenderOptions) -> AnimFileType {
+    match (options.save_gif, options.save_apng) {
+        (true, _) => AnimFileType::Gif,
+        (_, true) => AnimFileType::Apng,
+        _ => AnimFileType::None,
+    }
+}
+
+pub fn load_cgg_frames(unit_id: u32, input_path: &str) -> Result<Vec<cgg::FrameParts>> {
+    let in_cgg_file = |err: FfbeError| err.in_file(cgg::file_path(unit_id, input_path));
+    let reader = cgg::read_file(unit_id, input_path).map_err(|err| in_cgg_file(err.into()))?;
+
+    let mut frames = Vec::new();
+    for (row, line_result) in reader.lines().enumerate() {
+        let line = line_result.map_err(|err| in_cgg_file(err.into()))?;
+
+        match cgg::process(&line, row).map_err(in_cgg_file)? {
+            Some(frame_parts) => frames.push(frame_parts),
+            None => continue, // Skip empty lines
+        }
+    }
+
+    Ok(frames)
+}
+
+pub fn create_unit(unit_id: u32, frames: Vec<cgg::FrameParts>) -> Unit {
+    Unit {
+        id: unit_id,
+        frames,
+        ..Default::default()
+    }
+}
+
+fn process_animation_frames(
+    options: &RenderOptions,
+    uid: u32,
+    unit: &mut Unit,
+    src_img: &DynamicImage,
+    part_cache: &cgs::PartCache,
+    anim_name: &str,
+    progress: &dyn ProgressHook,
+) -> Result<Vec<cgs::CompositeFrame>> {
+    let cgs_frames_meta = load_cgs_metadata(uid, anim_name, &options.input_dir)?;
+    let cgs_file = cgs::file_path(uid, anim_name, &options.input_dir);
+    let frames = create_cgs_frames(cgs_frames_meta, unit, &cgs_file)?;
+    let composite_frames = process_frames_with_progress(
+        &frames,
+        src_img,
+        unit,
+        options.include_empty,
+        part_cache,
+        anim_name,
+        progress,
+    );
+
+    Ok(composite_frames)
+}
+
+/// Reads the lines of a cgs file, each with its row starting at 0.
+pub fn load_cgs_metadata(
+    unit_id: u32,
+    anim_name: &str,
+    input_path: &str,
+) -> Result<Vec<(usize, cgs::CgsMeta)>> {
+    let in_cgs_file = |err: FfbeError| err.in_file(cgs::file_path(unit_id, anim_name, input_path));
+    let reader =
+        cgs::read_file(unit_id, anim_name, input_path).map_err(|err| in_cgs_file(err.into()))?;
+
+    let mut cgs_frames_meta = Vec::new();
+    for (row, line_result) in reader.lines().enumerate() {
+        let line = line_result.map_err(|err| in_cgs_file(err.into()))?;
+
+        match cgs::process(&line, row) {
+            Some(Ok(meta)) => cgs_frames_meta.push((row, meta)),
+            Some(Err(err)) => return Err(in_cgs_file(err)),
+            None => continue, // Skip empty lines
+        }
+    }
+
+    Ok(cgs_frames_meta)
+}
+
+/// Looks up the cgg frame of every cgs line read from `cgs_file`. Fails when a line
+/// references a frame the cgg file does not have.
+fn create_cgs_frames(
+    cgs_frames_meta: Vec<(usize, cgs::CgsMeta)>,
+    unit: &Unit,
+    cgs_file: &Path,
+) -> Result<Vec<cgs::Frame>> {
+    cgs_frames_meta
+        .into_iter()
+        .map(|(row, meta)| {
+            let cgs::CgsMeta(frame_idx, frame_offset_x, frame_offset_y, delay) = meta;
+            cgs::check_frame_index(frame_idx, row, unit.frames.len())
+                .map_err(|err| err.in_file(cgs_file))?;
+            Ok(cgs::Frame {
+                frame_idx,
+                parts: unit.frames[frame_idx].clone(),
+                offset_x: frame_offset_x,
+                offset_y: frame_offset_y,
+                delay,
+            })
+        })
+        .collect()
+}
+
+/// The cgg parts of a frame built by `create_cgs_frames`.
+fn frame_parts(unit: &Unit, frame_idx: usize) -> Result<&cgg::FrameParts> {
+    unit.frames
+        .get(frame_idx)
+        .ok_or_else(|| FfbeError::MissingValue(format!("cgg frame {frame_idx}")))
+}
+
+fn calculate_frame_rect(unit: &Unit, layout: &LayoutOptions) -> Result<Rect> {
+    let top_left = unit
+        .top_left
+        .ok_or(FfbeError::MissingValue("top_left".to_string()))?;
+    let bottom_right = unit
+        .bottom_right
+        .ok_or(FfbeError::MissingValue("bottom_right".to_string()))?;
+
+    Ok(layout.frame_rect(top_left, bottom_right))
+}
+
+/// When `--shared-bounds` is set, measures the selected animations of the unit and
+/// returns the frame rect covering all of them. Each animation is then cropped to this
+/// rect so the character keeps the same position and size across sheets. Animations that
+/// fail to load are left out; they fail again, and are reported, when rendered.
+fn calculate_shared_frame_rect(ctx: &RenderContext, animations: &[String]) -> Option<Rect> {
+    let options = ctx.options;
+    let uid = ctx.unit.id;
+    if !options.shared_bounds {
+        return None;
+    }
+
+    let mut shared_unit = ctx.unit.clone();
+    for anim_name in animations {
+        let cgs_file = cgs::file_path(uid, anim_name, &options.input_dir);
+        let frames = load_cgs_metadata(uid, anim_name, &options.input_dir)
+            .and_then(|meta| create_cgs_frames(meta, &shared_unit, &cgs_file));
+        match frames {
+            Ok(frames) => {
+                cgs::measure_frames(&frames, &ctx.src_img, &mut shared_unit, &ctx.part_cache)
+            }
+            Err(err) => log::warn!("Leaving {anim_name} out of the shared bounds: {err:#}"),
+        }
+    }
+
+    // Without any content every animation keeps its own bounds
+    let shared_rect = calculate_frame_rect(&shared_unit, &options.layout).ok()?;
+    log::info!(
+        "Shared bounds for unit {uid}: {}x{} at ({}, {})",
+        shared_rect.width,
+        shared_rect.height,
+        shared_rect.x,
+        shared_rect.y
+    );
+    Some(shared_rect)
+}
+
+fn resize_empty_frames_to_bounds(frames: &mut [cgs::CompositeFrame], frame_rect: Rect) {
+    // All empty frames share a single transparent image with full frame dimensions
+    let full_frame = Arc::new(RgbaImage::new(frame_rect.width, frame_rect.height));
+
+    for frame in frames.iter_mut() {
+        if frame.is_empty() {
+            frame.image = Arc::clone(&full_frame);
+            frame.rect = frame_rect;
+        }
+    }
+}
+
+/// Cuts the content of every frame down to the part inside `frame_rect`. Frames keep
+/// only their content and its position; each output places it within its cell when the
+/// output is drawn, so no frame is held at full size.
+fn crop_frames_to_bounds(frames: &mut [cgs::CompositeFrame], frame_rect: Rect) {
+    // Frames that share a composited image also share the cropped result
+    let mut cropped: HashMap<*const RgbaImage, (Arc<RgbaImage>, Rect)> = HashMap::new();
+
+    for frame in frames.iter_mut() {
+        let rect = frame.rect;
+        let left = rect.x.max(frame_rect.x);
+        let top = rect.y.max(frame_rect.y);
+        let right = (rect.x + rect.width as i32).min(frame_rect.x + frame_rect.width as i32);
+        let bottom = (rect.y + rect.height as i32).min(frame_rect.y + frame_rect.height as i32);
+        if (left, top, right, bottom)
+            == (
+                rect.x,
+                rect.y,
+                rect.x + rect.width as i32,
+                rect.y + rect.height as i32,
+            )
+        {
+            continue;
+        }
+
+        let (image, rect) = cropped.entry(Arc::as_ptr(&frame.image)).or_insert_with(|| {
+            // Nothing of the content is inside the bounds
+            if left >= right || top >= bottom {
+                let rect = Rect {
+                    x: frame_rect.x,
+                    y: frame_rect.y,
+                    width: 1,
+                    height: 1,
+                };
+                return (Arc::new(RgbaImage::new(1, 1)), rect);
+            }
+            let cropped_rect = Rect {
+                x: left,
+                y: top,
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+            };
+            let image = imageops::crop_imm(
+                frame.image.as_ref(),
+                (left - rect.x) as u32,
+                (top - rect.y) as u32,
+                cropped_rect.width,
+                cropped_rect.height,
+            )
+            .to_image();
+            (Arc::new(image), cropped_rect)
+        });
+        frame.image = Arc::clone(image);
+        frame.rect = *rect;
+    }
+}
+
+/// Writes `{layers_dir}/{position}/` for every frame, holding one PNG per cgg part
+/// positioned on the frame canvas and upscaled like the other outputs.
+fn save_frame_layers(
+    options: &RenderOptions,
+    unit: &Unit,
+    src_img: &DynamicImage,
+    part_cache: &cgs::PartCache,
+    layers_dir: &Path,
+    frames: &[cgs::CompositeFrame],
+    frame_rect: Rect,
+) -> Result<()> {
+    for (position, frame) in frames.iter().enumerate() {
+        let mut frame_layers = layers::render_layers(
+            frame_parts(unit, frame.frame_idx)?,
+            frame.offset_x,
+            frame.offset_y,
+            src_img,
+            part_cache,
+            frame_rect,
+        );
+        if options.scale != 1 {
+            for layer in &mut frame_layers {
+                layer.image =
+                    crate::imageops::upscale(&layer.image, options.scale, options.scaler)?;
+            }
+        }
+        layers::save_layers(&frame_layers, &layers_dir.join(format!("{position:03}")))?;
+    }
+
+    Ok(())
+}
+
+fn draw_debug_overlay(
+    frames: &mut [cgs::CompositeFrame],
+    unit: &Unit,
+    frame_rect: Rect,
+) -> Result<()> {
+    // Frames with the same frame index and offset have the same image and overlay. Empty
+    // frames all share one image, so the image alone does not identify the overlay.
+    let mut overlaid: HashMap<(usize, i32, i32), Arc<RgbaImage>> = HashMap::new();
+
+    for frame in frames.iter_mut() {
+        let key = (frame.frame_idx, frame.offset_x, frame.offset_y);
+        let image = match overlaid.get(&key) {
+            Some(image) => Arc::clone(image),
+            None => {
+                // The overlay covers the whole frame, not just its content
+                let placed = Background::Transparent.place_frame(frame, frame_rect);
+                let image = Arc::new(crate::debug::draw_frame_overlay(
+                    &placed.image,
+                    frame_parts(unit, frame.frame_idx)?,
+                    frame.offset_x,
+                    frame.offset_y,
+                    frame_rect,
+                ));
+                overlaid.insert(key, Arc::clone(&image));
+                image
+            }
+        };
+        frame.image = image;
+        frame.rect = frame_rect;
+    }
+
+    Ok(())
+}
+
+fn upscale_frames(frames: &mut [cgs::CompositeFrame], scale: u32, scaler: Scaler) -> Result<()> {
+    if scale == 1 {
+        return Ok(());
+    }
+
+    // Frames that share an image also share the upscaled result
+    let mut scaled: HashMap<*const RgbaImage, Arc<RgbaImage>> = HashMap::new();
+
+    for frame in frames.iter_mut() {
+        let key = Arc::as_ptr(&frame.image);
+        let image = match scaled.get(&key) {
+            Some(image) => Arc::clone(image),
+            None => {
+                let image = Arc::new(crate::imageops::upscale(&frame.image, scale, scaler)?);
+                scaled.insert(key, Arc::clone(&image));
+                image
+            }
+        };
+        frame.image = image;
+        frame.rect = frame.rect.scaled(scale);
+    }
+
+    Ok(())
+}
+
+fn save_animated_files(
+    options: &RenderOptions,
+    outputs: &OutputPaths,
+    anim_name: &str,
+    frames: &[cgs::CompositeFrame],
+    cell_rect: Rect,
+    anim_file_type: AnimFileType,
+) -> Result<()> {
+    let frames = if options.collapse {
+        Cow::Owned(cgs::collapse_repeated_frames(frames))
+    } else {
+        Cow::Borrowed(frames)
+    };
+    // Each full frame is built only when the encoder takes it
+    let background = options.layout.background;
+    let full_frames = || {
+        frames
+            .iter()
+            .map(move |frame| background.place_frame(frame, cell_rect))
+    };
+
+    match anim_file_type {
+        AnimFileType::Apng => {
+            report_write(outputs.write(anim_name, OutputKind::Apng, |path| {
+                crate::imageops::encode_animated_apng(full_frames(), &path.to_string_lossy())
+            })?);
+        }
+        AnimFileType::Gif => {
+            report_write(outputs.write(anim_name, OutputKind::Gif, |path| {
+                crate::imageops::encode_animated_gif(full_frames(), &path.to_string_lossy())
+            })?);
+        }
+        AnimFileType::None => {}
+    }
+    Ok(())
+}
+
+fn create_spritesheet(
+    frames: &[cgs::CompositeFrame],
+    frame_rect: Rect,
+    columns: usize,
+    layout: &LayoutOptions,
+) -> RgbaImage {
+    if columns == 0 || columns >= frames.len() {
+        create_single_row_spritesheet(frames, frame_rect, layout)
+    } else {
+        create_multi_row_spritesheet(frames, frame_rect, columns, layout)
+    }
+}
+
+fn create_single_row_spritesheet(
+    frames: &[cgs::CompositeFrame],
+    frame_rect: Rect,
+    layout: &LayoutOptions,
+) -> RgbaImage {
+    create_multi_row_spritesheet(frames, frame_rect, frames.len().max(1), layout)
+}
+
+fn create_multi_row_spritesheet(
+    frames: &[cgs::CompositeFrame],
+    frame_rect: Rect,
+    columns: usize,
+    layout: &LayoutOptions,
+) -> RgbaImage {
+    let rows = frames.len().div_ceil(columns) as u32;
+    let (width, height) = layout.sheet_size(columns as u32, rows, frame_rect);
+    let mut sheet = layout.background.canvas(width, height);
+
+    for (idx, frame) in frames.iter().enumerate() {
+        let (x, y) = layout.cell_position(idx, columns, frame_rect);
+        imageops::overlay(
+            &mut sheet,
+            frame.image.as_ref(),
+            x as i64 + (frame.rect.x - frame_rect.x) as i64,
+            y as i64 + (frame.rect.y - frame_rect.y) as i64,
+        );
+    }
+
+    sheet
+}
+
+fn save_json_output(
+    options: &RenderOptions,
+    outputs: &OutputPaths,
+    anim_name: &str,
+    frames: &[cgs::CompositeFrame],
+    frame_rect: Rect,
+    shared_rect: Option<Rect>,
+    spritesheet: &RgbaImage,
+) -> Result<()> {
+    let mut animation_json = metadata::AnimationJson::from_frames(
+        outputs.uid,
+        anim_name.to_string(),
+        frames,
+        frame_rect,
+        spritesheet.width(),
+        spritesheet.height(),
+    );
+    animation_json.cell_spacing = options.layout.spacing;
+    animation_json.shared_bounds = shared_rect;
+    animation_json.scale(options.scale);
+
+    report_write(outputs.write(anim_name, OutputKind::Json, |path| {
+        metadata::save_animation_json(&animation_json, &path.to_string_lossy())
+    })?);
+    Ok(())
+}
+
+fn save_spritesheet(outputs: &OutputPaths, anim_name: &str, spritesheet: &RgbaImage) -> Result<()> {
+    report_write(outputs.write(anim_name, OutputKind::Spritesheet, |path| {
+        Ok(spritesheet.save(path)?)
+    })?);
+    Ok(())
+}
+
+/// Mentions outputs the overwrite policy kept.
+pub fn report_write(outcome: WriteOutcome) {
+    if let WriteOutcome::Kept(path) = outcome {
+        log::info!("Keeping existing {}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::FRAME_PADDING;
+    use crate::progress;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_determine_animation_file_type() {
+        let args_gif = RenderOptions {
+            save_gif: true,
+            save_apng: true,
+            ..Default::default()
+        };
+
+        let args_apng = RenderOptions {
+            save_gif: false,
+            save_apng: true,
+            ..args_gif.clone()
+        };
+
+        let args_none = RenderOptions {
+            save_gif: false,
+            save_apng: false,
+            ..args_gif.clone()
+        };
+
+        assert!(matches!(
+            determine_animation_file_type(&args_gif),
+            AnimFileType::Gif
+        ));
+        assert!(matches!(
+            determine_animation_file_type(&args_apng),
+            AnimFileType::Apng
+        ));
+        assert!(matches!(
+            determine_animation_file_type(&args_none),
+            AnimFileType::None
+        ));
+    }
+
+    #[test]
+    fn test_create_unit() {
+        let frames = vec![vec![]]; // Empty frame parts
+        let unit = create_unit(12345, frames);
+
+        assert_eq!(unit.id, 12345);
+        assert_eq!(unit.frames.len(), 1);
+        assert!(unit.top_left.is_none());
+        assert!(unit.bottom_right.is_none());
+    }
+
+    #[test]
+    fn test_calculate_frame_rect() {
+        let unit = Unit {
+            id: 123,
+            frames: vec![],
+            top_left: Some(crate::imageops::Point::new(10, 20)),
+            bottom_right: Some(crate::imageops::Point::new(110, 220)),
+            width: None,
+            height: None,
+            x_offset: None,
+            y_offset: None,
+        };
+
+        let rect = calculate_frame_rect(&unit, &LayoutOptions::default()).unwrap();
+        assert_eq!(rect.x, 10);
+        assert_eq!(rect.y, 20);
+        assert_eq!(rect.width, 100 + FRAME_PADDING);
+        assert_eq!(rect.height, 200 + FRAME_PADDING);
+
+        let padded = LayoutOptions {
+            padding: "5".parse().unwrap(),
+            ..Default::default()
+        };
+        let rect = calculate_frame_rect(&unit, &padded).unwrap();
+        assert_eq!(rect.x, 5);
+        assert_eq!(rect.y, 15);
+        assert_eq!(rect.width, 110);
+        assert_eq!(rect.height, 210);
+    }
+
+    #[test]
+    fn test_calculate_frame_rect_missing_bounds() {
+        let unit = Unit::default();
+        let result = calculate_frame_rect(&unit, &LayoutOptions::default());
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Missing value: top_left")
+        );
+    }
+
+    #[test]
+    fn test_create_single_row_spritesheet() {
+        let frames = vec![
+            cgs::CompositeFrame {
+                frame_idx: 0,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+            cgs::CompositeFrame {
+                frame_idx: 1,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+        ];
+
+        let frame_rect = Rect {
+            x: 0,
+            y: 0,
+            width: 50,
+            height: 50,
+        };
+        let sheet = create_single_row_spritesheet(&frames, frame_rect, &LayoutOptions::default());
+
+        assert_eq!(sheet.width(), 100); // 2 frames * 50 width
+        assert_eq!(sheet.height(), 50);
+    }
+
+    #[test]
+    fn test_create_multi_row_spritesheet() {
+        let frames = vec![
+            cgs::CompositeFrame {
+                frame_idx: 0,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+            cgs::CompositeFrame {
+                frame_idx: 1,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+            cgs::CompositeFrame {
+                frame_idx: 2,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+        ];
+
+        let frame_rect = Rect {
+            x: 0,
+            y: 0,
+            width: 50,
+            height: 50,
+        };
+        let sheet = create_multi_row_spritesheet(&frames, frame_rect, 2, &LayoutOptions::default());
+
+        assert_eq!(sheet.width(), 100); // 2 columns * 50 width
+        assert_eq!(sheet.height(), 100); // 2 rows * 50 height (3 frames, 2 columns = 2 rows)
+
+        let spaced = LayoutOptions {
+            spacing: 4,
+            ..Default::default()
+        };
+        let sheet = create_multi_row_spritesheet(&frames, frame_rect, 2, &spaced);
+
+        assert_eq!(sheet.width(), 104); // 2 columns * 50 width + 1 gap
+        assert_eq!(sheet.height(), 104); // 2 rows * 50 height + 1 gap
+    }
+
+    #[test]
+    fn test_load_cgg_frames_nonexistent() {
+        let result = load_cgg_frames(99999, "nonexistent_path");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_cgg_frames_existing() {
+        let result = load_cgg_frames(204000103, "test_data");
+        assert!(result.is_ok());
+
+        let frames = result.unwrap();
+        assert!(!frames.is_empty());
+    }
+
+    #[test]
+    fn test_save_spritesheet() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        let outputs = OutputPaths::new(temp_path, OutputTemplate::default(), 123);
+
+        let spritesheet = RgbaImage::new(100, 100);
+        let result = save_spritesheet(&outputs, "test", &spritesheet);
+
+        assert!(result.is_ok());
+
+        let expected_path = format!("{}/123-test.png", temp_path);
+        assert!(std::path::Path::new(&expected_path).exists());
+    }
+
+    #[test]
+    fn test_plan_outputs_matches_spritesheet() {
+        let frame_rect = Rect {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 8,
+        };
+        let frames: Vec<_> = (0..5)
+            .map(|frame_idx| cgs::CompositeFrame {
+                frame_idx,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(10, 8).into(),
+                rect: frame_rect,
+                delay: 2,
+            })
+            .collect();
+
+        for columns in [0, 2, 5, 9] {
+            let options = RenderOptions {
+                columns,
+                layout: LayoutOptions {
+                    spacing: 3,
+                    ..Default::default()
+                },
+                save_gif: true,
+                save_json: true,
+                ..Default::default()
+            };
+            let outputs = OutputPaths::new("out", OutputTemplate::default(), 123);
+            let plan = plan_outputs(
+                &options,
+                &outputs,
+                "atk",
+                frames.len(),
+                frame_rect,
+                determine_animation_file_type(&options),
+            );
+            let sheet = create_spritesheet(&frames, frame_rect, options.columns, &options.layout);
+
+            let kinds: Vec<_> = plan.iter().map(|output| output.kind).collect();
+            assert_eq!(
+                kinds,
+                vec![OutputKind::Spritesheet, OutputKind::Gif, OutputKind::Json]
+            );
+            assert_eq!(plan[0].path, std::path::Path::new("out/123-atk.png"));
+            assert_eq!(plan[0].size, Some(sheet.dimensions()), "columns {columns}");
+            assert_eq!(plan[1].size, Some((10, 8)));
+            assert_eq!(plan[2].size, None);
+        }
+    }
+
+    #[test]
+    fn test_is_up_to_date() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let args = RenderOptions {
+            save_json: true,
+            ..Default::default()
+        };
+        let outputs = OutputPaths::new(temp_path, OutputTemplate::default(), 123);
+        let mut manifest = Manifest::default();
+        manifest.record(Manifest::key(123, "atk"), "hash".to_string());
+
+        let up_to_date = |args: &RenderOptions, hash: &str| {
+            is_up_to_date(args, &outputs, &manifest, "atk", hash, AnimFileType::None)
+        };
+
+        // Outputs missing
+        assert!(!up_to_date(&args, "hash"));
+        std::fs::write(temp_dir.path().join("123-atk.png"), "").unwrap();
+        assert!(!up_to_date(&args, "hash"));
+        std::fs::write(temp_dir.path().join("123-atk.json"), "").unwrap();
+        assert!(up_to_date(&args, "hash"));
+
+        assert!(!up_to_date(&args, "changed"));
+        let forced = RenderOptions {
+            force: true,
+            ..args.clone()
+        };
+        assert!(!up_to_date(&forced, "hash"));
+    }
+
+    #[test]
+    fn test_save_spritesheet_with_filename_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        let template = "{uid}/{anim}/{format}.{ext}".parse().unwrap();
+        let outputs = OutputPaths::new(temp_path, template, 123);
+
+        save_spritesheet(&outputs, "test", &RgbaImage::new(10, 10)).unwrap();
+        assert!(temp_dir.path().join("123/test/sheet.png").exists());
+
+        assert!("{unit}.{ext}".parse::<OutputTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_resize_empty_frames_to_bounds() {
+        let mut frames = vec![
+            cgs::CompositeFrame {
+                frame_idx: 0,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(1, 1).into(), // Empty frame (1x1)
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 1,
+                    height: 1,
+                },
+                delay: 100,
+            },
+            cgs::CompositeFrame {
+                frame_idx: 1,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(), // Normal frame
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+        ];
+
+        let frame_rect = Rect {
+            x: 0,
+            y: 0,
+            width: 50,
+            height: 50,
+        };
+        resize_empty_frames_to_bounds(&mut frames, frame_rect);
+
+        // Empty frame should now be resized to full dimensions
+        assert_eq!(frames[0].image.width(), 50);
+        assert_eq!(frames[0].image.height(), 50);
+        assert_eq!(frames[0].rect.width, 50);
+        assert_eq!(frames[0].rect.height, 50);
+
+        // Normal frame should remain unchanged
+        assert_eq!(frames[1].image.width(), 50);
+        assert_eq!(frames[1].image.height(), 50);
+    }
+
+    #[test]
+    fn test_crop_frames_to_bounds_cuts_content() {
+        let mut content = RgbaImage::new(4, 1);
+        content.put_pixel(3, 0, image::Rgba([255, 0, 0, 255]));
+        let frame = |x| cgs::CompositeFrame {
+            frame_idx: 0,
+            offset_x: 0,
+            offset_y: 0,
+            image: Arc::new(content.clone()),
+            rect: Rect {
+                x,
+                y: 1001,
+                width: 4,
+                height: 1,
+            },
+            delay: 1,
+        };
+        let inside = frame(1001);
+        let spilling = frame(1003);
+        let mut frames = vec![inside.clone(), spilling.clone(), spilling];
+        let frame_rect = Rect {
+            x: 1000,
+            y: 1000,
+            width: 6,
+            height: 4,
+        };
+
+        crop_frames_to_bounds(&mut frames, frame_rect);
+
+        // Content inside the bounds is kept as it is
+        assert!(Arc::ptr_eq(&frames[0].image, &inside.image));
+        assert_eq!(frames[0].rect, inside.rect);
+
+        // Content past the bounds is cut off, and repeated frames share the result
+        assert_eq!(frames[1].image.dimensions(), (3, 1));
+        assert_eq!(frames[1].rect.x, 1003);
+        assert_eq!(frames[1].rect.width, 3);
+        assert!(frames[1].image.pixels().all(|pixel| pixel.0[3] == 0));
+        assert!(Arc::ptr_eq(&frames[1].image, &frames[2].image));
+
+        let sheet =
+            create_single_row_spritesheet(&frames[..1], frame_rect, &LayoutOptions::default());
+        assert_eq!(sheet.dimensions(), (6, 4));
+        assert_eq!(*sheet.get_pixel(4, 1), image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_calculate_shared_frame_rect() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        // Two short animations that use different cgg frames at different offsets
+        for file in ["unit_anime_204000103.png", "unit_cgg_204000103.csv"] {
+            std::fs::copy(format!("test_data/{file}"), format!("{temp_path}/{file}")).unwrap();
+        }
+        std::fs::write(
+            format!("{temp_path}/unit_atk_cgs_204000103.csv"),
+            "32,0,0,5,\n33,0,0,10,\n",
+        )
+        .unwrap();
+        std::fs::write(
+            format!("{temp_path}/unit_move_cgs_204000103.csv"),
+            "0,40,-20,5,\n",
+        )
+        .unwrap();
+        std::fs::write(
+            format!("{temp_path}/unit_bad_cgs_204000103.csv"),
+            "9999,0,0,5\n",
+        )
+        .unwrap();
+
+        let args = RenderOptions {
+            input_dir: temp_path.to_string(),
+            shared_bounds: true,
+            ..Default::default()
+        };
+
+        let frames = load_cgg_frames(204000103, &args.input_dir).unwrap();
+        let ctx = RenderContext::new(
+            &args,
+            OutputPaths::new(temp_path, OutputTemplate::default(), 204000103),
+            create_unit(204000103, frames),
+            crate::imageops::load_source_image(204000103, temp_path).unwrap(),
+            &progress::no_progress,
+        );
+        let anim_rect = |anim_name| {
+            let mut anim_unit = ctx.unit.clone();
+            process_animation_frames(
+                &args,
+                204000103,
+                &mut anim_unit,
+                &ctx.src_img,
+                &ctx.part_cache,
+                anim_name,
+                &progress::no_progress,
+            )
+            .unwrap();
+            calculate_frame_rect(&anim_unit, &LayoutOptions::default()).unwrap()
+        };
+        let selected =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+        // The broken animation is left out instead of failing the unit
+        let shared_rect =
+            calculate_shared_frame_rect(&ctx, &selected(&["atk", "move", "bad"])).unwrap();
+
+        // The shared rect must contain the bounds of every individual animation
+        for anim_name in ["atk", "move"] {
+            let rect = anim_rect(anim_name);
+            assert!(shared_rect.x <= rect.x);
+            assert!(shared_rect.y <= rect.y);
+            assert!(shared_rect.x + shared_rect.width as i32 >= rect.x + rect.width as i32);
+            assert!(shared_rect.y + shared_rect.height as i32 >= rect.y + rect.height as i32);
+        }
+
+        // Only the selected animations count
+        assert_eq!(
+            calculate_shared_frame_rect(&ctx, &selected(&["atk"])),
+            Some(anim_rect("atk"))
+        );
+        assert_eq!(calculate_shared_frame_rect(&ctx, &selected(&["bad"])), None);
+
+        let no_shared_args = RenderOptions {
+            shared_bounds: false,
+            ..args.clone()
+        };
+        let ctx = RenderContext {
+            options: &no_shared_args,
+            ..ctx
+        };
+        assert!(calculate_shared_frame_rect(&ctx, &selected(&["atk"])).is_none());
+    }
+
+    #[test]
+    fn test_draw_debug_overlay_on_empty_frames() {
+        // The canvas origin lands at (20, 20) of the frame
+        let origin = crate::constants::HALF_CANVAS as i32 - 20;
+        let frame_rect = Rect {
+            x: origin,
+            y: origin,
+            width: 40,
+            height: 40,
+        };
+        let empty_frame = |offset_x| {
+            cgs::Frame {
+                frame_idx: 0,
+                parts: Vec::new(),
+                offset_x,
+                offset_y: 0,
+                delay: 2,
+            }
+            .composite(Arc::new(RgbaImage::new(1, 1)), Rect::default())
+        };
+        let mut frames = vec![empty_frame(0), empty_frame(10), empty_frame(0)];
+        resize_empty_frames_to_bounds(&mut frames, frame_rect);
+        let unit = create_unit(1, vec![Vec::new()]);
+
+        draw_debug_overlay(&mut frames, &unit, frame_rect).unwrap();
+
+        // Each offset gets its own origin cross, repeated frames share the overlay
+        assert_ne!(frames[0].image, frames[1].image);
+        assert!(Arc::ptr_eq(&frames[0].image, &frames[2].image));
+        for (frame, cross_x) in [(&frames[0], 20), (&frames[1], 30)] {
+            assert_eq!(
+                frame.image.get_pixel(cross_x, 20),
+                &crate::debug::ORIGIN_COLOR
+            );
+        }
+        assert_eq!(frames[1].image.get_pixel(20, 20).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_upscale_frames_keeps_shared_images() {
+        let image = Arc::new(RgbaImage::new(10, 5));
+        let frame = cgs::CompositeFrame {
+            frame_idx: 0,
+            offset_x: 0,
+            offset_y: 0,
+            image,
+            rect: Rect::default(),
+            delay: 1,
+        };
+        let mut frames = vec![frame.clone(), frame];
+
+        upscale_frames(&mut frames, 3, Scaler::Nearest).unwrap();
+
+        assert_eq!(frames[0].image.dimensions(), (30, 15));
+        assert!(Arc::ptr_eq(&frames[0].image, &frames[1].image));
+    }
+
+    #[test]
+    fn test_save_json_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+
+        let args = RenderOptions {
+            save_json: true,
+            ..Default::default()
+        };
+
+        let frames = vec![
+            cgs::CompositeFrame {
+                frame_idx: 0,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 10,
+                    y: 20,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 100,
+            },
+            cgs::CompositeFrame {
+                frame_idx: 1,
+                offset_x: 0,
+                offset_y: 0,
+                image: RgbaImage::new(50, 50).into(),
+                rect: Rect {
+                    x: 10,
+                    y: 20,
+                    width: 50,
+                    height: 50,
+                },
+                delay: 150,
+            },
+        ];
+
+        let frame_rect = Rect {
+            x: 5,
+            y: 10,
+            width: 60,
+            height: 70,
+        };
+        let spritesheet = RgbaImage::new(120, 70);
+
+        let outputs = OutputPaths::new(temp_path, OutputTemplate::default(), 123);
+        let result = save_json_output(
+            &args,
+            &outputs,
+            "test_anim",
+            &frames,
+            frame_rect,
+            None,
+            &spritesheet,
+        );
+        assert!(result.is_ok());
+
+        let expected_path = format!("{}/123-test_anim.json", temp_path);
+        assert!(std::path::Path::new(&expected_path).exists());
+
+        // Parse and validate JSON structure
+        let json_content = std::fs::read_to_string(&expected_path).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json_content).unwrap();
+
+        // Validate all required fields exist and have correct values
+        assert_eq!(parsed["unitId"], 123);
+        assert_eq!(parsed["animName"], "test_anim");
+        assert_eq!(parsed["frameDelays"], serde_json::json!([100, 150]));
+        assert_eq!(parsed["frameRect"]["x"], 5);
+        assert_eq!(parsed["frameRect"]["y"], 10);
+        assert_eq!(parsed["frameRect"]["width"], 60);
+        assert_eq!(parsed["frameRect"]["height"], 70);
+        assert_eq!(parsed["imageWidth"], 120);
+        assert_eq!(parsed["imageHeight"], 70);
+        assert_eq!(parsed["origin"]["x"], 995);
+        assert_eq!(parsed["origin"]["y"], 990);
+        assert_eq!(parsed["frameOrigins"].as_array().unwrap().len(), 2);
+
+        // Ensure no extra fields
+        let expected_keys = [
+            "unitId",
+            "animName",
+            "frameDelays",
+            "frameRect",
+            "imageWidth",
+            "imageHeight",
+            "origin",
+            "frameOrigins",
+        ];
+        assert_eq!(parsed.as_object().unwrap().len(), expected_keys.len());
+        for key in expected_keys {
+            assert!(parsed.as_object().unwrap().contains_key(key));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{FfbeError, Result, output::OutputAction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationStatus {
+    Rendered,
+    /// Unchanged since the last render, see [`crate::manifest`].
+    Skipped,
+    /// Measured but not written by a dry run.
+    Planned,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    /// [`FfbeError::kind`] of the error.
+    pub kind: &'static str,
+    /// The error followed by its causes.
+    pub message: String,
+    /// The file, line and field the error was found at, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'static str>,
+}
+
+impl From<&FfbeError> for ErrorReport {
+    fn from(err: &FfbeError) -> Self {
+        Self {
+            kind: err.kind(),
+            message: format!("{err:#}"),
+            path: err.path().map(Path::to_path_buf),
+            line: err.line(),
+            field: err.field(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputReport {
+    pub path: PathBuf,
+    /// The `{format}` of the output, see [`crate::output::OutputKind::format`].
+    pub format: &'static str,
+    pub action: OutputAction,
+    /// Size of the file on disk; `None` for the layers folder and outputs not written.
+    pub bytes: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationReport {
+    pub unit_id: u32,
+    pub name: String,
+    pub status: AnimationStatus,
+    pub error: Option<ErrorReport>,
+    /// Number of frames after parsing, including empty frames when they are kept.
+    pub frames: Option<usize>,
+    pub outputs: Vec<OutputReport>,
+    pub duration_ms: u64,
+}
+
+impl AnimationReport {
+    pub fn new(unit_id: u32, name: &str, status: AnimationStatus) -> Self {
+        Self {
+            unit_id,
+            name: name.to_string(),
+            status,
+            error: None,
+            frames: None,
+            outputs: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitReport {
+    /// The unit id or character name as requested.
+    pub unit: String,
+    pub unit_id: Option<u32>,
+    /// Why the unit could not be rendered at all, or why the run failed.
+    pub error: Option<ErrorReport>,
+    pub animations: Vec<AnimationReport>,
+    pub duration_ms: u64,
+}
+
+impl UnitReport {
+    pub fn count(&self, status: AnimationStatus) -> usize {
+        self.animations
+            .iter()
+            .filter(|animation| animation.status == status)
+            .count()
+    }
+}
+
+/// Report of a whole run, written by `--report`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    pub success: bool,
+    pub rendered: usize,
+    pub skipped: usize,
+    pub planned: usize,
+    pub failed: usize,
+    pub units: Vec<UnitReport>,
+}
+
+impl RunReport {
+    pub fn new(units: Vec<UnitReport>) -> Self {
+        let count = |status| units.iter().map(|unit| unit.count(status)).sum();
+        let failed = count(AnimationStatus::Failed);
+
+        Self {
+            success: failed == 0 && units.iter().all(|unit| unit.error.is_none()),
+            rendered: count(AnimationStatus::Rendered),
+            skipped: count(AnimationStatus::Skipped),
+            planned: count(AnimationStatus::Planned),
+            failed,
+            units,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        crate::output::write_atomic(path, |temp_path| Ok(std::fs::write(temp_path, json)?))
+    }
+}
+
+/// One line of the NDJSON progress stream.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum ProgressEvent<'a> {
+    /// An animation finished, was skipped or failed.
+    Animation(&'a AnimationReport),
+    /// A unit finished; its animations were already streamed.
+    Unit {
+        unit: &'a str,
+        #[serde(rename = "unitId")]
+        unit_id: Option<u32>,
+        error: Option<&'a ErrorReport>,
+        #[serde(rename = "durationMs")]
+        duration_ms: u64,
+    },
+}
+
+/// Collects the report of a unit, streaming each event as one JSON line when a progress
+/// stream is open.
+pub struct Reporter {
+    pub unit: UnitReport,
+    stream: Option<Box<dyn Write + Send>>,
+}
+
+impl Reporter {
+    /// Streams progress to `path`, or to stdout for `-`, when given.
+    pub fn new(unit: &str, path: Option<&Path>) -> Result<Self> {
+        let stream: Option<Box<dyn Write + Send>> = match path {
+            None => None,
+            Some(path) if path == Path::new("-") => Some(Box::new(io::stdout())),
+            Some(path) => Some(Box::new(File::create(path)?)),
+        };
+
+        Ok(Self {
+            unit: UnitReport {
+                unit: unit.to_string(),
+                ..Default::default()
+            },
+            stream,
+        })
+    }
+
+    pub fn animation(&mut self, report: AnimationReport) -> Result<()> {
+        self.emit(&ProgressEvent::Animation(&report))?;
+        self.unit.animations.push(report);
+        Ok(())
+    }
+
+    /// Streams the unit summary and returns the finished report.
+    pub fn finish(mut self) -> Result<UnitReport> {
+        let event = ProgressEvent::Unit {
+            unit: &self.unit.unit,
+            unit_id: self.unit.unit_id,
+            error: self.unit.error.as_ref(),
+            duration_ms: self.unit.duration_ms,
+        };
+        let line = serde_json::to_string(&event)?;
+        if let Some(stream) = &mut self.stream {
+            writeln!(stream, "{line}")?;
+            stream.flush()?;
+        }
+        Ok(self.unit)
+    }
+
+    fn emit(&mut self, event: &ProgressEvent) -> Result<()> {
+        if let Some(stream) = &mut self.stream {
+            writeln!(stream, "{}", serde_json::to_string(event)?)?;
+            stream.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reporter_streams_ndjson() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("progress.ndjson");
+
+        let mut reporter = Reporter::new("Rain", Some(&path)).unwrap();
+        reporter.unit.unit_id = Some(100000102);
+        reporter
+            .animation(AnimationReport::new(
+                100000102,
+                "atk",
+                AnimationStatus::Rendered,
+            ))
+            .unwrap();
+        let mut failed = AnimationReport::new(100000102, "idle", AnimationStatus::Failed);
+        let err = FfbeError::InvalidField {
+            line: 2,
+            field: "delay",
+            value: "x".to_string(),
+        };
+        failed.error = Some(ErrorReport::from(
+            &err.in_file("unit_idle_cgs_100000102.csv"),
+        ));
+        reporter.animation(failed).unwrap();
+        let unit = reporter.finish().unwrap();
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["event"], "animation");
+        assert_eq!(lines[0]["status"], "rendered");
+        assert_eq!(lines[1]["error"]["kind"], "parse");
+        assert_eq!(lines[1]["error"]["path"], "unit_idle_cgs_100000102.csv");
+        assert_eq!(lines[1]["error"]["line"], 2);
+        assert_eq!(lines[1]["error"]["field"], "delay");
+        assert!(lines[0]["error"].is_null());
+        assert_eq!(lines[2]["event"], "unit");
+        assert_eq!(lines[2]["unitId"], 100000102);
+
+        let report = RunReport::new(vec![unit]);
+        assert!(!report.success);
+        assert_eq!((report.rendered, report.failed), (1, 1));
+
+        let report_path = temp_dir.path().join("report.json");
+        report.save(&report_path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(report_path).unwrap()).unwrap();
+        assert_eq!(json["units"][0]["animations"][1]["name"], "idle");
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{FfbeError, Result};
+use crate::{
+    FfbeError, Result,
+    imageops::{MAX_SCALE, Scaler},
+};
 use std::path::Path;
 
 pub fn validate_input_args(uid: u32, input_dir: &str, anim_name: Option<&str>) -> Result<()> {
@@ -56,6 +59,21 @@ pub fn validate_output_dir(output_dir: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn validate_scale(scale: u32, scaler: Scaler) -> Result<()> {
+    if scale > MAX_SCALE {
+        return Err(FfbeError::InvalidInput(format!(
+            "Scale factor {scale} is larger than the maximum of {MAX_SCALE}"
+        )));
+    }
+    if !scaler.supports(scale) {
+        return Err(FfbeError::InvalidInput(format!(
+            "Scale factor {scale} is not supported by the {scaler} scaler"
+        )));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,4 +207,28 @@ mod tests {
         // Clean up
         fs::remove_file(temp_file).unwrap();
     }
+
+    #[test]
+    fn test_validate_scale() {
+        assert!(validate_scale(1, Scaler::Nearest).is_ok());
+        assert!(validate_scale(6, Scaler::Nearest).is_ok());
+        assert!(validate_scale(4, Scaler::Scale2x).is_ok());
+
+        let result = validate_scale(0, Scaler::Nearest);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Scale factor 0 is not supported")
+        );
+        assert!(validate_scale(5, Scaler::Scale2x).is_err());
+        assert!(validate_scale(MAX_SCALE, Scaler::Nearest).is_ok());
+        assert!(
+            validate_scale(70000, Scaler::Nearest)
+                .unwrap_err()
+                .to_string()
+                .contains("larger than the maximum of 16")
+        );
+    }
 }
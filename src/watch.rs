@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::Result;
+
+/// Whether `file_name` is one of the unit's input files: its cgg file, its atlas or one of
+/// its cgs files.
+pub fn is_unit_file(file_name: &str, unit_id: u32) -> bool {
+    file_name == format!("unit_cgg_{unit_id}.csv")
+        || file_name == format!("unit_anime_{unit_id}.png")
+        || (file_name.starts_with("unit_") && file_name.ends_with(&format!("_cgs_{unit_id}.csv")))
+}
+
+/// Modification time and size of every input file of a unit, used to poll for changes.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Snapshot(BTreeMap<PathBuf, (Option<SystemTime>, u64)>);
+
+impl Snapshot {
+    pub fn capture(unit_id: u32, input_dir: &str) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(input_dir)? {
+            let entry = entry?;
+            if !is_unit_file(&entry.file_name().to_string_lossy(), unit_id) {
+                continue;
+            }
+            // Files can disappear between listing and reading while an editor saves them
+            if let Ok(metadata) = entry.metadata() {
+                files.insert(entry.path(), (metadata.modified().ok(), metadata.len()));
+            }
+        }
+        Ok(Self(files))
+    }
+
+    /// Files added, removed or modified since `previous`, in path order.
+    pub fn changed_files(&self, previous: &Snapshot) -> Vec<PathBuf> {
+        let modified = self
+            .0
+            .iter()
+            .filter(|(path, stamp)| previous.0.get(*path) != Some(stamp))
+            .map(|(path, _)| path);
+        let removed = previous.0.keys().filter(|path| !self.0.contains_key(*path));
+
+        let mut changed: Vec<PathBuf> = modified.chain(removed).cloned().collect();
+        changed.sort();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unit_file() {
+        assert!(is_unit_file("unit_cgg_123.csv", 123));
+        assert!(is_unit_file("unit_anime_123.png", 123));
+        assert!(is_unit_file("unit_limit_atk_cgs_123.csv", 123));
+        assert!(!is_unit_file("unit_cgg_1234.csv", 123));
+        assert!(!is_unit_file("unit_atk_cgs_456.csv", 123));
+        assert!(!is_unit_file("123-atk.png", 123));
+    }
+
+    #[test]
+    fn test_snapshot_changed_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap();
+        let cgs = temp_dir.path().join("unit_atk_cgs_1.csv");
+        let cgg = temp_dir.path().join("unit_cgg_1.csv");
+        fs::write(&cgs, "0,0,0,2\n").unwrap();
+        fs::write(&cgg, "").unwrap();
+        fs::write(temp_dir.path().join("unit_cgg_2.csv"), "").unwrap();
+
+        let before = Snapshot::capture(1, temp_path).unwrap();
+        assert!(
+            Snapshot::capture(1, temp_path)
+                .unwrap()
+                .changed_files(&before)
+                .is_empty()
+        );
+
+        fs::write(&cgs, "0,0,0,20\n").unwrap();
+        fs::remove_file(&cgg).unwrap();
+        let idle = temp_dir.path().join("unit_idle_cgs_1.csv");
+        fs::write(&idle, "0,0,0,2\n").unwrap();
+        fs::write(temp_dir.path().join("unit_cgg_2.csv"), "changed").unwrap();
+
+        let after = Snapshot::capture(1, temp_path).unwrap();
+        assert_eq!(after.changed_files(&before), vec![cgs, cgg, idle]);
+    }
+}
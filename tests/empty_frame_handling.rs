@@ -185,6 +185,7 @@ fn test_spritesheet_empty_frame_handling() {
 }
 
 /// Helper function to extract width from identify command output
+#[allow(clippy::collapsible_if)]
 fn extract_width_from_identify(output: &str) -> u32 {
     // identify output format: "filename PNG 1234x567 8-bit/color RGBA, non-interlaced"
     for line in output.lines() {